@@ -0,0 +1,239 @@
+//! Typed column handles of Yoshino schemas.
+//!
+//! `#[derive(Schema)]` generates a struct of `Column` handles for every schema struct, which
+//! can be obtained with the generated `columns()` method:
+//!
+//! ```text
+//! let cond = Counter::columns().stock.eq(20) | Counter::columns().name.starts_with("m");
+//! ```
+//!
+//! A handle only provides the conditions that are valid for the type of its field, so a
+//! renamed field or a comparison with a value of the wrong type is found by the compiler.
+
+use crate::db::DbValue;
+use crate::types::{Field, RowID};
+use crate::Cond;
+use std::marker::PhantomData;
+
+/// The handle of a field of type `T` in a schema, which creates conditions on the field.
+pub struct Column<T> {
+    name: &'static str,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Column<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Column<T> {}
+
+impl<T> std::fmt::Debug for Column<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Column").field(&self.name).finish()
+    }
+}
+
+/// A value that can be compared with a field of type `T`. Besides the values of `T`, the
+/// values of `U` can be compared with a nullable field of type `Option<U>`, and `&str` can be
+/// compared with `String` and `Option<String>` fields.
+pub trait ColumnValue<T> {
+    /// Convert the value to the `DbValue` to be compared with the field.
+    fn into_db_value(self) -> DbValue;
+}
+
+impl<T: Field> ColumnValue<T> for T {
+    fn into_db_value(self) -> DbValue {
+        self.to_db_data().into()
+    }
+}
+
+impl<T: Field> ColumnValue<Option<T>> for T {
+    fn into_db_value(self) -> DbValue {
+        self.to_db_data().into()
+    }
+}
+
+impl ColumnValue<String> for &str {
+    fn into_db_value(self) -> DbValue {
+        DbValue::from(self)
+    }
+}
+
+impl ColumnValue<Option<String>> for &str {
+    fn into_db_value(self) -> DbValue {
+        DbValue::from(self)
+    }
+}
+
+/// A field type whose values are ordered, so it can be compared with `gt`, `lt` and so on.
+pub trait OrderedField: Field {}
+
+/// A field type stored as a text, so it can be matched with text patterns.
+pub trait TextualField: Field {}
+
+impl OrderedField for i64 {}
+impl OrderedField for i32 {}
+impl OrderedField for i16 {}
+impl OrderedField for i8 {}
+impl OrderedField for u32 {}
+impl OrderedField for u16 {}
+impl OrderedField for u8 {}
+impl OrderedField for f64 {}
+impl OrderedField for f32 {}
+impl OrderedField for String {}
+impl OrderedField for RowID {}
+impl<T: OrderedField> OrderedField for Option<T> {}
+
+impl TextualField for String {}
+impl<T: TextualField> TextualField for Option<T> {}
+
+impl<T: Field> Column<T> {
+    /// Create the handle of the field `name`. It's called by the code generated by
+    /// `#[derive(Schema)]`.
+    pub fn new(name: &'static str) -> Column<T> {
+        Column {
+            name,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The name of the field.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The field is equal to `value`, or the field is null if `value` is null.
+    pub fn eq<V: ColumnValue<T>>(&self, value: V) -> Cond {
+        match value.into_db_value() {
+            DbValue::Null => Cond::is_null(self.name),
+            value => Cond::EqualTo {
+                field_name: self.name.to_string(),
+                value,
+            },
+        }
+    }
+
+    /// The field is not equal to `value`, or the field is not null if `value` is null.
+    /// Like SQL, a null field is neither equal nor not equal to a non-null value.
+    pub fn ne<V: ColumnValue<T>>(&self, value: V) -> Cond {
+        match value.into_db_value() {
+            DbValue::Null => Cond::is_not_null(self.name),
+            value => Cond::NotEqualTo {
+                field_name: self.name.to_string(),
+                value,
+            },
+        }
+    }
+
+    /// The field is equal to one of `values`.
+    pub fn is_in<V: ColumnValue<T>, I: IntoIterator<Item = V>>(&self, values: I) -> Cond {
+        Cond::In {
+            field_name: self.name.to_string(),
+            values: values.into_iter().map(V::into_db_value).collect(),
+        }
+    }
+
+    /// The field is equal to none of `values`.
+    pub fn not_in<V: ColumnValue<T>, I: IntoIterator<Item = V>>(&self, values: I) -> Cond {
+        Cond::NotIn {
+            field_name: self.name.to_string(),
+            values: values.into_iter().map(V::into_db_value).collect(),
+        }
+    }
+}
+
+impl<T: Field> Column<Option<T>> {
+    /// The field is null.
+    pub fn is_null(&self) -> Cond {
+        Cond::is_null(self.name)
+    }
+
+    /// The field is not null.
+    pub fn is_not_null(&self) -> Cond {
+        Cond::is_not_null(self.name)
+    }
+}
+
+impl<T: OrderedField> Column<T> {
+    /// The field is greater than `value`.
+    pub fn gt<V: ColumnValue<T>>(&self, value: V) -> Cond {
+        Cond::GreaterThan {
+            field_name: self.name.to_string(),
+            value: value.into_db_value(),
+        }
+    }
+
+    /// The field is less than `value`.
+    pub fn lt<V: ColumnValue<T>>(&self, value: V) -> Cond {
+        Cond::LessThan {
+            field_name: self.name.to_string(),
+            value: value.into_db_value(),
+        }
+    }
+
+    /// The field is greater than or equal to `value`.
+    pub fn ge<V: ColumnValue<T>>(&self, value: V) -> Cond {
+        Cond::GreaterThanOrEqualTo {
+            field_name: self.name.to_string(),
+            value: value.into_db_value(),
+        }
+    }
+
+    /// The field is less than or equal to `value`.
+    pub fn le<V: ColumnValue<T>>(&self, value: V) -> Cond {
+        Cond::LessThanOrEqualTo {
+            field_name: self.name.to_string(),
+            value: value.into_db_value(),
+        }
+    }
+
+    /// The field is between `start` and `end`, inclusively.
+    pub fn between<V: ColumnValue<T>>(&self, start: V, end: V) -> Cond {
+        Cond::Between {
+            field_name: self.name.to_string(),
+            start: start.into_db_value(),
+            end: end.into_db_value(),
+        }
+    }
+}
+
+impl<T: TextualField> Column<T> {
+    /// The field is equal to `value` ignoring the case of ASCII letters.
+    pub fn eq_ignore_case(&self, value: &str) -> Cond {
+        Cond::text_equal_to_ignore_case(self.name, value)
+    }
+
+    /// The field matches the SQL LIKE `pattern`, whose wildcards are escaped by the default
+    /// escape character of the database. See `Cond::like`.
+    pub fn like(&self, pattern: &str) -> Cond {
+        Cond::like(self.name, pattern)
+    }
+
+    /// The field matches the SQL LIKE `pattern`, whose wildcards are escaped by
+    /// `LIKE_ESCAPE_CHAR`. See `Cond::like_with_escape`.
+    pub fn like_with_escape(&self, pattern: &str) -> Cond {
+        Cond::like_with_escape(self.name, pattern)
+    }
+
+    /// The field matches the glob `pattern` case sensitively. See `Cond::glob`.
+    pub fn glob(&self, pattern: &str) -> Cond {
+        Cond::glob(self.name, pattern)
+    }
+
+    /// The field starts with `value`. See `Cond::starts_with`.
+    pub fn starts_with(&self, value: &str) -> Cond {
+        Cond::starts_with(self.name, value)
+    }
+
+    /// The field ends with `value`. See `Cond::ends_with`.
+    pub fn ends_with(&self, value: &str) -> Cond {
+        Cond::ends_with(self.name, value)
+    }
+
+    /// The field contains `value`. See `Cond::contains`.
+    pub fn contains(&self, value: &str) -> Cond {
+        Cond::contains(self.name, value)
+    }
+}
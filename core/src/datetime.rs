@@ -0,0 +1,99 @@
+//! Date and time field types, available with the `chrono` feature.
+//!
+//! Date times and dates are stored as ISO-8601 texts with fixed width, such as
+//! `2023-01-02T03:04:05.000006` and `2023-01-02`, so that they can be compared as texts.
+//! Database adaptors may store them in native date time columns instead.
+//! `DateTime<Utc>` is stored in UTC without the time zone.
+//!
+//! Date times are stored with microsecond precision, so nanoseconds are truncated and a value
+//! read back may differ from the one written. Only years 0 to 9999 have the fixed width, and
+//! dates out of this range are not ordered correctly. MySQL limits the years further to
+//! 1000 to 9999.
+//!
+//! `Duration` is stored as an integer of microseconds. Durations beyond the range of it
+//! (about 292,000 years) are saturated.
+
+use crate::column::OrderedField;
+use crate::db::{DbDataType, DbError, DbValue};
+use crate::types::{get_type_mismatch_error, Field, TimeField};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.6f";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+impl Field for NaiveDateTime {
+    type Storage = String;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        match data {
+            DbValue::Text(s) => {
+                // MySQL separates the date and the time with a space
+                NaiveDateTime::parse_from_str(&s.replacen(' ', "T", 1), "%Y-%m-%dT%H:%M:%S%.f")
+                    .map_err(|_| get_type_mismatch_error("a date time text", data))
+            }
+            _ => Err(get_type_mismatch_error("a date time text", data)),
+        }
+    }
+    fn to_db_data(&self) -> String {
+        self.format(DATETIME_FORMAT).to_string()
+    }
+    fn db_field_type() -> DbDataType {
+        DbDataType::DateTime
+    }
+}
+
+impl Field for DateTime<Utc> {
+    type Storage = String;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        NaiveDateTime::from_db_data(data).map(|datetime| Utc.from_utc_datetime(&datetime))
+    }
+    fn to_db_data(&self) -> String {
+        self.naive_utc().to_db_data()
+    }
+    fn db_field_type() -> DbDataType {
+        DbDataType::DateTime
+    }
+}
+
+impl Field for NaiveDate {
+    type Storage = String;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        match data {
+            DbValue::Text(s) => NaiveDate::parse_from_str(s, DATE_FORMAT)
+                .map_err(|_| get_type_mismatch_error("a date text", data)),
+            _ => Err(get_type_mismatch_error("a date text", data)),
+        }
+    }
+    fn to_db_data(&self) -> String {
+        self.format(DATE_FORMAT).to_string()
+    }
+    fn db_field_type() -> DbDataType {
+        DbDataType::Date
+    }
+}
+
+impl Field for Duration {
+    type Storage = i64;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        i64::from_db_data(data).map(Duration::microseconds)
+    }
+    fn to_db_data(&self) -> i64 {
+        match self.num_microseconds() {
+            Some(v) => v,
+            None if *self < Duration::zero() => i64::MIN,
+            None => i64::MAX,
+        }
+    }
+    fn db_field_type() -> DbDataType {
+        DbDataType::Int
+    }
+}
+
+impl TimeField for NaiveDateTime {}
+impl TimeField for DateTime<Utc> {}
+impl TimeField for NaiveDate {}
+impl TimeField for Duration {}
+
+impl OrderedField for NaiveDateTime {}
+impl OrderedField for DateTime<Utc> {}
+impl OrderedField for NaiveDate {}
+impl OrderedField for Duration {}
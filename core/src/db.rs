@@ -1,256 +1,541 @@
 //! Database related core stuff
+use crate::query::Query;
 use crate::query_cond::Cond;
 use crate::{RowID};
-use std::ptr;
+
+/// Kind of database errors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DbErrorKind {
+    /// A constraint other than unique keys is violated, e.g. NOT NULL or foreign keys.
+    ConstraintViolation,
+    /// A unique key or primary key is violated.
+    UniqueViolation,
+    /// The database is busy or locked by another connection or transaction.
+    Busy,
+    /// The record to operate on is not found.
+    NotFound,
+    /// The data type of a value doesn't match the field.
+    TypeMismatch,
+    /// The connection to the database is lost or can't be established.
+    ConnectionLost,
+    /// The SQL statement has a syntax error.
+    Syntax,
+    /// Other errors.
+    Other,
+}
 
 /// Database error
 #[derive(Debug, Clone)]
-pub struct DbError(pub String);
+pub struct DbError {
+    /// Kind of the error.
+    pub kind: DbErrorKind,
+    /// The native error code of the database engine, if any.
+    pub code: Option<i32>,
+    /// The error message.
+    pub message: String,
+    /// The SQL statement that failed, if any.
+    pub sql: Option<String>,
+}
+
+impl DbError {
+    /// Create an error of the kind with the message.
+    pub fn new(kind: DbErrorKind, message: String) -> DbError {
+        DbError {
+            kind,
+            code: None,
+            message,
+            sql: None,
+        }
+    }
+
+    /// Attach the native error code of the database engine to the error.
+    pub fn with_code(mut self, code: i32) -> DbError {
+        self.code = Some(code);
+        self
+    }
+
+    /// Prefix the error message with the schema and field where the error occurs.
+    pub fn in_field(mut self, schema_name: &str, field_name: &str) -> DbError {
+        self.message = format!("{}.{}: {}", schema_name, field_name, self.message);
+        self
+    }
+
+    /// Attach the SQL statement that failed to the error.
+    pub fn with_sql(mut self, sql: &str) -> DbError {
+        self.sql = Some(sql.to_string());
+        self
+    }
+}
+
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(code) = self.code {
+            write!(f, " (error code {})", code)?;
+        }
+        if let Some(sql) = &self.sql {
+            write!(f, " in SQL: {}", sql)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DbError {}
 
 /// Yoshino database adaptor trait.
 ///
 /// Every database adaptor implementation should implement this trait.
 pub trait DbAdaptor {
-    type Iterator<T: crate::types::Schema>: Iterator<Item = T>;
+    /// Iterator over the query results. It yields an error if reading a record fails. The
+    /// iteration ends after an error of the database engine, while a record that fails to be
+    /// decoded is reported and the iteration can go on to the next record. The iterator borrows
+    /// the adaptor, so it can't outlive the connection.
+    type Iterator<'a, T: crate::types::Schema>: Iterator<Item = Result<T, DbError>>
+    where
+        Self: 'a;
     /// Create data table in the database for a Yoshino schema.
+    ///
+    /// MySQL commits the active transaction implicitly before creating a table, so the MySQL
+    /// adaptor returns an error if it's called inside a transaction.
     fn create_table_for_schema<T: crate::types::Schema>(&mut self) -> Result<(), DbError>;
-    /// Insert a record to the database.
-    fn insert_record<T: crate::types::Schema>(&mut self, record: T) -> Result<(), DbError>;
+    /// Drop the data table of a Yoshino schema if it exists. Like `create_table_for_schema`,
+    /// the MySQL adaptor returns an error if it's called inside a transaction.
+    fn drop_table_for_schema<T: crate::types::Schema>(&mut self) -> Result<(), DbError>;
+    /// Remove all records from the data table of a Yoshino schema, keeping the table.
+    ///
+    /// The row ids of new records start from 1 again. Like `create_table_for_schema`, the
+    /// MySQL adaptor returns an error if it's called inside a transaction.
+    fn truncate_table_for_schema<T: crate::types::Schema>(&mut self) -> Result<(), DbError>;
+    /// Check whether the data table of a Yoshino schema exists in the database.
+    fn table_exists_for_schema<T: crate::types::Schema>(&mut self) -> Result<bool, DbError>;
+    /// Insert a record to the database and return the row id assigned to it. The keys that
+    /// are generated at insert time, like `UuidKey::NewV4`, are generated here, but they are
+    /// not returned. Use `save`, which writes them back to the record, or `UuidKey::generate`
+    /// before inserting the record to know them.
+    ///
+    /// Returns `RowID::NEW` if the schema has no RowID field.
+    fn insert_record<T: crate::types::Schema>(&mut self, mut record: T) -> Result<RowID, DbError> {
+        record.generate_keys();
+        self.insert_values::<T>(record.get_values())
+    }
+    /// Insert a record of the schema given as the values of all its fields, in the order of
+    /// `get_fields`, and return the row id assigned to it. No key is generated.
+    ///
+    /// Returns `RowID::NEW` if the schema has no RowID field.
+    fn insert_values<T: crate::types::Schema>(
+        &mut self,
+        values: Vec<DbValue>,
+    ) -> Result<RowID, DbError>;
+    /// Insert a batch of records to the database in one transaction and return the number of
+    /// inserted records. Either all records are inserted or none of them is.
+    fn insert_records<T: crate::types::Schema, I: IntoIterator<Item = T>>(
+        &mut self,
+        records: I,
+    ) -> Result<usize, DbError>;
+    /// Insert a record, or update the existing record that has the same unique key. The
+    /// unique key is the one declared by the schema, or the primary key field (RowID or
+    /// UuidKey) if there is none.
+    ///
+    /// Return the row id of the inserted or updated record, or `RowID::NEW` if the schema has no
+    /// RowID field. Like `insert_record`, the generated keys are not returned.
+    fn upsert_record<T: crate::types::Schema>(&mut self, record: T) -> Result<RowID, DbError>;
+    /// Query records of the schema with the condition, ordering and pagination of `query`.
+    fn query<T: crate::types::Schema>(
+        &mut self,
+        query: Query<T>,
+    ) -> Result<Self::Iterator<'_, T>, DbError>;
     /// Query all records of the schema.
-    fn query_all<T: crate::types::Schema>(&mut self) -> Result<Self::Iterator<T>, DbError>;
+    fn query_all<T: crate::types::Schema>(&mut self) -> Result<Self::Iterator<'_, T>, DbError> {
+        self.query(Query::all())
+    }
     /// Query records of the schema that matches the condition.
     fn query_with_cond<T: crate::types::Schema>(
         &mut self,
         cond: Cond,
-    ) -> Result<Self::Iterator<T>, DbError>;
-    /// Delete records of the schema that matches the condition.
-    fn delete_with_cond<T: crate::types::Schema>(&mut self, cond: Cond) -> Result<(), DbError>;
-    /// Update records of the schema that matches the condition.
+    ) -> Result<Self::Iterator<'_, T>, DbError> {
+        self.query(Query::filter(cond))
+    }
+    /// Get the record of the schema with the row id. Return `None` if there is no such record.
+    fn get_by_row_id<T: crate::types::Schema>(&mut self, id: i64) -> Result<Option<T>, DbError> {
+        let field_name = match T::get_row_id_field_name() {
+            Some(field_name) => field_name,
+            None => {
+                return Err(DbError::new(
+                    DbErrorKind::Other,
+                    format!("{} has no RowID field", T::get_schema_name()),
+                ))
+            }
+        };
+        let query = Query::filter(Cond::integer_equal_to(&field_name, id)).limit(1);
+        self.query::<T>(query)?.next().transpose()
+    }
+    /// Find the only record of the schema that matches the condition. Return `None` if no
+    /// record matches, or an error if more than one record matches.
+    fn find_one<T: crate::types::Schema>(&mut self, cond: Cond) -> Result<Option<T>, DbError> {
+        let mut records = self.query::<T>(Query::filter(cond).limit(2))?;
+        let record = records.next().transpose()?;
+        if records.next().transpose()?.is_some() {
+            return Err(DbError::new(
+                DbErrorKind::Other,
+                format!(
+                    "More than one record of {} matches the condition",
+                    T::get_schema_name()
+                ),
+            ));
+        }
+        Ok(record)
+    }
+    /// Save the record. It's inserted if its primary key (RowID or UuidKey) is new, and the
+    /// assigned row id or the generated key is written back to the record. Otherwise the
+    /// record with the same primary key is updated.
+    fn save<T: crate::types::Schema>(&mut self, record: &mut T) -> Result<(), DbError> {
+        if T::get_primary_key_field_name().is_none() {
+            return Err(DbError::new(
+                DbErrorKind::Other,
+                format!("{} has no primary key field", T::get_schema_name()),
+            ));
+        }
+        match Cond::is_primary_key_equal_to(record) {
+            None => {
+                record.generate_keys();
+                let row_id = self.insert_values::<T>(record.get_values())?;
+                record.set_row_id(row_id);
+                Ok(())
+            }
+            Some(cond) => match self.update_values_with_cond::<T>(cond, record.get_values())? {
+                0 => Err(DbError::new(
+                    DbErrorKind::NotFound,
+                    format!("No record of {} with the primary key", T::get_schema_name()),
+                )),
+                _ => Ok(()),
+            },
+        }
+    }
+    /// Delete the record with the same primary key (RowID or UuidKey) as `record`.
+    fn delete<T: crate::types::Schema>(&mut self, record: &T) -> Result<(), DbError> {
+        let cond = match Cond::is_primary_key_equal_to(record) {
+            Some(cond) => cond,
+            None => {
+                return Err(DbError::new(
+                    DbErrorKind::Other,
+                    format!("The record of {} has no primary key", T::get_schema_name()),
+                ))
+            }
+        };
+        match self.delete_with_cond::<T>(cond)? {
+            0 => Err(DbError::new(
+                DbErrorKind::NotFound,
+                format!("No record of {} with the primary key", T::get_schema_name()),
+            )),
+            _ => Ok(()),
+        }
+    }
+    /// Count records of the schema that matches the condition, or all records if `cond` is `None`.
+    fn count<T: crate::types::Schema>(&mut self, cond: Option<Cond>) -> Result<i64, DbError>;
+    /// Compute the aggregate function over a numeric field of records of the schema that
+    /// matches the condition, or all records if `cond` is `None`.
+    ///
+    /// The result is an integer if `Aggregate::has_integer_result` says so, and a float
+    /// otherwise. Returns `None` if there is no matched record.
+    fn aggregate<T: crate::types::Schema>(
+        &mut self,
+        aggregate: Aggregate,
+        field_name: &str,
+        cond: Option<Cond>,
+    ) -> Result<Option<DbValue>, DbError>;
+    /// Count all records of the schema.
+    fn count_all<T: crate::types::Schema>(&mut self) -> Result<i64, DbError> {
+        self.count::<T>(None)
+    }
+    /// Count records of the schema that matches the condition.
+    fn count_with_cond<T: crate::types::Schema>(&mut self, cond: Cond) -> Result<i64, DbError> {
+        self.count::<T>(Some(cond))
+    }
+    /// Sum of a numeric field of records of the schema that matches the condition.
+    fn sum_with_cond<T: crate::types::Schema>(
+        &mut self,
+        field_name: &str,
+        cond: Cond,
+    ) -> Result<Option<DbValue>, DbError> {
+        self.aggregate::<T>(Aggregate::Sum, field_name, Some(cond))
+    }
+    /// Minimum of a numeric field of records of the schema that matches the condition.
+    fn min_with_cond<T: crate::types::Schema>(
+        &mut self,
+        field_name: &str,
+        cond: Cond,
+    ) -> Result<Option<DbValue>, DbError> {
+        self.aggregate::<T>(Aggregate::Min, field_name, Some(cond))
+    }
+    /// Maximum of a numeric field of records of the schema that matches the condition.
+    fn max_with_cond<T: crate::types::Schema>(
+        &mut self,
+        field_name: &str,
+        cond: Cond,
+    ) -> Result<Option<DbValue>, DbError> {
+        self.aggregate::<T>(Aggregate::Max, field_name, Some(cond))
+    }
+    /// Average of a numeric field of records of the schema that matches the condition.
+    fn avg_with_cond<T: crate::types::Schema>(
+        &mut self,
+        field_name: &str,
+        cond: Cond,
+    ) -> Result<Option<DbValue>, DbError> {
+        self.aggregate::<T>(Aggregate::Avg, field_name, Some(cond))
+    }
+    /// Delete records of the schema that matches the condition and return the number of
+    /// deleted records.
+    fn delete_with_cond<T: crate::types::Schema>(
+        &mut self,
+        cond: Cond,
+    ) -> Result<usize, DbError>;
+    /// Update records of the schema that matches the condition and return the number of
+    /// matched records.
     fn update_with_cond<T: crate::types::Schema>(
         &mut self,
         cond: Cond,
         record: T,
-    ) -> Result<(), DbError>;
+    ) -> Result<usize, DbError> {
+        self.update_values_with_cond::<T>(cond, record.get_values())
+    }
+    /// Update records of the schema that matches the condition with the values of all fields,
+    /// in the order of `get_fields`, and return the number of matched records.
+    fn update_values_with_cond<T: crate::types::Schema>(
+        &mut self,
+        cond: Cond,
+        values: Vec<DbValue>,
+    ) -> Result<usize, DbError>;
+    /// Begin a transaction. Calling it inside an active transaction creates a savepoint,
+    /// so transactions can be nested.
+    fn begin_transaction(&mut self) -> Result<(), DbError>;
+    /// Commit the innermost active transaction.
+    fn commit(&mut self) -> Result<(), DbError>;
+    /// Roll back the innermost active transaction.
+    fn rollback(&mut self) -> Result<(), DbError>;
+    /// Run `f` inside a transaction.
+    ///
+    /// The transaction is committed if `f` returns `Ok`, and rolled back if `f` returns `Err`
+    /// or panics. A panic is resumed after the rollback.
+    fn transaction<R, F>(&mut self, f: F) -> Result<R, DbError>
+    where
+        Self: Sized,
+        F: FnOnce(&mut Self) -> Result<R, DbError>,
+    {
+        self.begin_transaction()?;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(&mut *self)));
+        match result {
+            Ok(Ok(value)) => match self.commit() {
+                Ok(()) => Ok(value),
+                Err(e) => {
+                    let _ = self.rollback();
+                    Err(e)
+                }
+            },
+            Ok(Err(e)) => {
+                let _ = self.rollback();
+                Err(e)
+            }
+            Err(panic) => {
+                let _ = self.rollback();
+                std::panic::resume_unwind(panic)
+            }
+        }
+    }
+}
+
+/// Aggregate functions over a numeric field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+impl Aggregate {
+    /// Whether the result over a field of `field_type` is an integer. The sum, minimum and
+    /// maximum of an integer field are integers, so they don't lose precision, while averages
+    /// are always floats.
+    pub fn has_integer_result(&self, field_type: DbDataType) -> bool {
+        field_type.is_integer() && *self != Aggregate::Avg
+    }
 }
 
 /// Database data type supported by Yoshino.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DbDataType {
     NullableText,
     NullableInt,
     Text,
     Int,
     Float,
+    NullableFloat,
     Binary,
     NullableBinary,
-    RowID 
+    RowID,
+    Bool,
+    NullableBool,
+    Int8,
+    NullableInt8,
+    Int16,
+    NullableInt16,
+    Int32,
+    NullableInt32,
+    UInt8,
+    NullableUInt8,
+    UInt16,
+    NullableUInt16,
+    UInt32,
+    NullableUInt32,
+    Float32,
+    NullableFloat32,
+    DateTime,
+    NullableDateTime,
+    Date,
+    NullableDate,
+    Uuid,
+    NullableUuid,
+    UuidKey,
+    Json,
+    NullableJson,
 }
 
-/// The mark trait to indicate that this type can be directly obtained from / sent to the databases.
-pub trait DbData {
-    /// Data type in `DbDataType`
-    fn db_data_type(&self) -> DbDataType;
-    /// A pointer to the data. The data should be raw data in contiguous memory.
-    /// For null values, it should return a null pointer. 
-    fn db_data_ptr(&self) -> *const core::ffi::c_void;
-    /// Data length in bytes.
-    fn db_data_len(&self) -> usize;
-    /// To restore data from a boxed db data object. 
-    /// A copy of the value should be created as this method only takes a reference as the parameter.
-    fn from_boxed_db_data(src: &Box<dyn DbData>) -> Self where Self: Sized;
-}
-
-impl DbData for String {
-    fn db_data_type(&self) -> DbDataType {
-        DbDataType::Text
-    }
-
-    fn db_data_ptr(&self) -> *const core::ffi::c_void {
-        self.as_ptr() as *const core::ffi::c_void
-    }
-
-    fn db_data_len(&self) -> usize {
-        self.len()
-    }
-
-    fn from_boxed_db_data(src: &Box<dyn DbData>) -> String {
-        unsafe {
-            {
-                let str_len = src.db_data_len();
-                let str_copy = libc::malloc(str_len) as *mut i8;
-                libc::strncpy(str_copy, src.db_data_ptr() as *mut i8, str_len);
-                String::from_raw_parts(str_copy as *mut u8, str_len, str_len)
-            }
+impl DbDataType {
+    /// The nullable version of this type. Nullable types and `RowID` are returned as they are.
+    /// `UuidKey` is mapped to `NullableUuid`, as a key can't be null.
+    pub fn nullable(&self) -> DbDataType {
+        match self {
+            DbDataType::Text => DbDataType::NullableText,
+            DbDataType::Int => DbDataType::NullableInt,
+            DbDataType::Float => DbDataType::NullableFloat,
+            DbDataType::Binary => DbDataType::NullableBinary,
+            DbDataType::Bool => DbDataType::NullableBool,
+            DbDataType::Int8 => DbDataType::NullableInt8,
+            DbDataType::Int16 => DbDataType::NullableInt16,
+            DbDataType::Int32 => DbDataType::NullableInt32,
+            DbDataType::UInt8 => DbDataType::NullableUInt8,
+            DbDataType::UInt16 => DbDataType::NullableUInt16,
+            DbDataType::UInt32 => DbDataType::NullableUInt32,
+            DbDataType::Float32 => DbDataType::NullableFloat32,
+            DbDataType::DateTime => DbDataType::NullableDateTime,
+            DbDataType::Date => DbDataType::NullableDate,
+            DbDataType::Uuid | DbDataType::UuidKey => DbDataType::NullableUuid,
+            DbDataType::Json => DbDataType::NullableJson,
+            _ => *self,
         }
     }
-}
-
-impl DbData for i64 {
-    fn db_data_type(&self) -> DbDataType {
-        DbDataType::Int
-    }
 
-    fn db_data_ptr(&self) -> *const core::ffi::c_void {
-        self as *const i64 as *const core::ffi::c_void
+    /// Whether the data of this type is stored as an integer. Booleans are stored as 0 or 1.
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            DbDataType::Int
+                | DbDataType::NullableInt
+                | DbDataType::RowID
+                | DbDataType::Bool
+                | DbDataType::NullableBool
+                | DbDataType::Int8
+                | DbDataType::NullableInt8
+                | DbDataType::Int16
+                | DbDataType::NullableInt16
+                | DbDataType::Int32
+                | DbDataType::NullableInt32
+                | DbDataType::UInt8
+                | DbDataType::NullableUInt8
+                | DbDataType::UInt16
+                | DbDataType::NullableUInt16
+                | DbDataType::UInt32
+                | DbDataType::NullableUInt32
+        )
     }
 
-    fn db_data_len(&self) -> usize {
-        8
+    /// Whether the data of this type is stored as a floating point number.
+    pub fn is_float(&self) -> bool {
+        matches!(
+            self,
+            DbDataType::Float
+                | DbDataType::NullableFloat
+                | DbDataType::Float32
+                | DbDataType::NullableFloat32
+        )
     }
 
-    fn from_boxed_db_data(src: &Box<dyn DbData>) -> i64 {
-        unsafe { *(src.db_data_ptr() as *const i64) }
+    /// Whether the data of this type is a number. Booleans are not numbers.
+    pub fn is_numeric(&self) -> bool {
+        let is_bool = matches!(self, DbDataType::Bool | DbDataType::NullableBool);
+        (self.is_integer() && !is_bool) || self.is_float()
     }
 }
 
-impl DbData for Option<i64> {
-    fn db_data_type(&self) -> DbDataType {
-        DbDataType::NullableInt
-    }
-
-    fn db_data_ptr(&self) -> *const core::ffi::c_void {
-        match self {
-            None => ptr::null(),
-            Some(v) => v as *const i64 as *const core::ffi::c_void
-        }
-    }
-
-    fn db_data_len(&self) -> usize {
-        8
-    }
-
-    fn from_boxed_db_data(src: &Box<dyn DbData>) -> Option<i64> {
-        if src.db_data_ptr().is_null() {
-            None
-        } else {
-            Some(unsafe { *(src.db_data_ptr() as *const i64) })
-        }
-    }
+/// A value that can be sent to or obtained from the databases.
+///
+/// Field types are converted to and from `DbValue` by the `Field` trait, and database adaptors
+/// bind and read `DbValue` objects.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DbValue {
+    /// The null value.
+    Null,
+    /// A 64-bit integer.
+    Int(i64),
+    /// A 64-bit floating point number.
+    Float(f64),
+    /// A text string.
+    Text(String),
+    /// A binary large object.
+    Blob(Vec<u8>),
 }
 
-impl DbData for Option<String> {
-    fn db_data_type(&self) -> DbDataType {
-        DbDataType::NullableText
-    }
-
-    fn db_data_ptr(&self) -> *const core::ffi::c_void {
-        match self {
-            None => ptr::null(),
-            Some(s) => s.as_ptr() as *const core::ffi::c_void,
-        }
-    }
-
-    fn db_data_len(&self) -> usize {
-        match self {
-            None => 0,
-            Some(s) => s.len(),
-        }
-    }
-
-    fn from_boxed_db_data(src: &Box<dyn DbData>) -> Option<String> {
-        if src.db_data_ptr().is_null() {
-            None
-        } else {
-            Some(unsafe {
-                let str_len = src.db_data_len();
-                let str_copy = libc::malloc(str_len) as *mut i8;
-                libc::strncpy(str_copy, src.db_data_ptr() as *mut i8, str_len);
-                String::from_raw_parts(str_copy as *mut u8, str_len, str_len)
-            })
-        }
+impl DbValue {
+    /// Whether the value is null.
+    pub fn is_null(&self) -> bool {
+        matches!(self, DbValue::Null)
     }
 }
 
-impl DbData for crate::types::RowID {
-    fn db_data_type(&self) -> DbDataType {
-        DbDataType::RowID
-    }
-    fn db_data_ptr(&self) -> *const core::ffi::c_void {
-        match self {
-            RowID::NEW => ptr::null(),
-            RowID::ID(v) => v as *const i64 as *const core::ffi::c_void,
-        }
-    }
-    fn db_data_len(&self) -> usize {
-        8
-    }
-
-    fn from_boxed_db_data(src: &Box<dyn DbData>) -> RowID {
-        if src.db_data_ptr().is_null() {
-            RowID::NEW
-        } else {
-            unsafe { RowID::ID(*(src.db_data_ptr() as *const i64)) }
-        }
+impl From<i64> for DbValue {
+    fn from(value: i64) -> DbValue {
+        DbValue::Int(value)
     }
 }
 
-impl DbData for f64 {
-    fn db_data_type(&self) -> DbDataType {
-        DbDataType::Float
-    }
-    fn db_data_ptr(&self) -> *const core::ffi::c_void {
-        self as *const f64 as *const core::ffi::c_void
-    }
-    fn db_data_len(&self) -> usize {
-        8
-    }
-    fn from_boxed_db_data(src: &Box<dyn DbData>) -> Self
-    where
-        Self: Sized,
-    {
-        unsafe { *(src.db_data_ptr() as *const f64) }
+impl From<f64> for DbValue {
+    fn from(value: f64) -> DbValue {
+        DbValue::Float(value)
     }
 }
 
-impl DbData for Vec<u8> {
-    fn db_data_type(&self) -> DbDataType {
-        DbDataType::Binary
-    }
-
-    fn db_data_ptr(&self) -> *const core::ffi::c_void {
-        self.as_ptr() as *mut u8 as *const core::ffi::c_void
-    }
-
-    fn db_data_len(&self) -> usize {
-        self.len()
-    }
-
-    fn from_boxed_db_data(src: &Box<dyn DbData>) -> Vec<u8> {
-        let len = src.db_data_len();
-        let ptr = src.db_data_ptr() as *const u8;
-        let mut buf = Vec::with_capacity(len);
-        for i in 0..len {
-            unsafe{
-                buf.push(*(ptr.offset(i as isize)))
-            }
-        }
-        buf
+impl From<String> for DbValue {
+    fn from(value: String) -> DbValue {
+        DbValue::Text(value)
     }
 }
 
-impl DbData for Option<Vec<u8>> {
-    fn db_data_type(&self) -> DbDataType {
-        DbDataType::NullableBinary
+impl From<&str> for DbValue {
+    fn from(value: &str) -> DbValue {
+        DbValue::Text(value.to_string())
     }
+}
 
-    fn db_data_ptr(&self) -> *const core::ffi::c_void {
-        match self {
-            None => ptr::null(),
-            Some(v) => v.as_ptr() as *mut u8 as *const core::ffi::c_void
-        }
+impl From<Vec<u8>> for DbValue {
+    fn from(value: Vec<u8>) -> DbValue {
+        DbValue::Blob(value)
     }
+}
 
-    fn db_data_len(&self) -> usize {
-        match self {
-            None => 0,
-            Some(v) => v.len()
+impl From<RowID> for DbValue {
+    fn from(value: RowID) -> DbValue {
+        match value {
+            RowID::NEW => DbValue::Null,
+            RowID::ID(id) => DbValue::Int(id),
         }
     }
+}
 
-    fn from_boxed_db_data(src: &Box<dyn DbData>) -> Self where Self: Sized {
-        if src.db_data_ptr().is_null() {
-            None
-        } else {
-            Some(Vec::<u8>::from_boxed_db_data(src))
+impl<T: Into<DbValue>> From<Option<T>> for DbValue {
+    fn from(value: Option<T>) -> DbValue {
+        match value {
+            None => DbValue::Null,
+            Some(v) => v.into(),
         }
     }
 }
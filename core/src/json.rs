@@ -0,0 +1,94 @@
+//! JSON field type, available with the `json` feature.
+
+use crate::column::Column;
+use crate::db::{DbDataType, DbError, DbErrorKind, DbValue};
+use crate::query_cond::Cond;
+use crate::types::{get_type_mismatch_error, Field};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::ops::Deref;
+
+/// A field of any serializable value, which is stored as a JSON text.
+///
+/// The value is serialized when the field is created by `Json::new`, so a value that can't be
+/// serialized, e.g. a map with non-string keys, is rejected there instead of when the record
+/// is written. Nested keys of the value can be filtered with `Cond::json_path_equal_to`.
+#[derive(Clone, Debug)]
+pub struct Json<T> {
+    value: T,
+    text: String,
+}
+
+impl<T: Serialize> Json<T> {
+    /// Create a JSON field of `value`. Return an error if the value can't be serialized.
+    pub fn new(value: T) -> Result<Json<T>, DbError> {
+        match serde_json::to_string(&value) {
+            Ok(text) => Ok(Json { value, text }),
+            Err(e) => Err(DbError::new(
+                DbErrorKind::TypeMismatch,
+                format!("Failed to serialize the JSON field: {}", e),
+            )),
+        }
+    }
+}
+
+impl<T> Json<T> {
+    /// Take the value out of the field.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+/// Two fields are equal if their values are, whatever their JSON texts are, as the database may
+/// reformat the text.
+impl<T: PartialEq> PartialEq for Json<T> {
+    fn eq(&self, other: &Json<T>) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Field for Json<T> {
+    type Storage = String;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        match data {
+            DbValue::Text(s) => match serde_json::from_str(s) {
+                Ok(value) => Ok(Json {
+                    value,
+                    text: s.clone(),
+                }),
+                Err(e) => Err(get_type_mismatch_error(
+                    &format!("a valid JSON text ({})", e),
+                    data,
+                )),
+            },
+            _ => Err(get_type_mismatch_error("a JSON text", data)),
+        }
+    }
+    fn to_db_data(&self) -> String {
+        self.text.clone()
+    }
+    fn db_field_type() -> DbDataType {
+        DbDataType::Json
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Column<Json<T>> {
+    /// The value at `path` in the field is equal to `value`. See `Cond::json_path_equal_to`.
+    pub fn json_path_eq<V: Into<DbValue>>(&self, path: &str, value: V) -> Cond {
+        Cond::json_path_equal_to(self.name(), path, value)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Column<Option<Json<T>>> {
+    /// The value at `path` in the field is equal to `value`. See `Cond::json_path_equal_to`.
+    pub fn json_path_eq<V: Into<DbValue>>(&self, path: &str, value: V) -> Cond {
+        Cond::json_path_equal_to(self.name(), path, value)
+    }
+}
@@ -3,9 +3,23 @@
 //! This is the core crate. Find the document at our
 //! [repo](https://github.com/linmx0130/yoshino).
 
+pub mod column;
+#[cfg(feature = "chrono")]
+pub mod datetime;
 pub mod db;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod query;
 pub mod query_cond;
 pub mod types;
+#[cfg(feature = "uuid")]
+pub mod uuid_field;
+pub use column::Column;
 pub use types::Schema;
-pub use types::{IntegerField, TextField, NullableTextField, NullableIntegerField, RowID, FloatField, BinaryField, NullableBinaryField};
+pub use types::{Field, RowID, TimeField};
+#[cfg(feature = "json")]
+pub use json::Json;
+#[cfg(feature = "uuid")]
+pub use uuid_field::UuidKey;
+pub use query::{Order, Query};
 pub use query_cond::Cond;
\ No newline at end of file
@@ -0,0 +1,73 @@
+//! Yoshino queries with ordering and pagination
+
+use crate::query_cond::Cond;
+use crate::Schema;
+use std::marker::PhantomData;
+
+/// Sort order of query results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+    /// Ascending order.
+    Asc,
+    /// Descending order.
+    Desc,
+}
+
+/// A query for records of the schema `T`.
+///
+/// A query is built from an optional condition, and then refined with ordering and
+/// pagination:
+/// ```text
+/// Query::<Record>::filter(cond).order_by("score", Order::Desc).limit(20).offset(40)
+/// ```
+/// All queries will be intepreted by database adaptors.
+pub struct Query<T: Schema> {
+    /// The condition that the records should match. `None` for all records.
+    pub cond: Option<Cond>,
+    /// The fields to sort the records by, in the order of priority.
+    pub order_by: Vec<(String, Order)>,
+    /// The maximum number of records to return.
+    pub limit: Option<u64>,
+    /// The number of records to skip.
+    pub offset: Option<u64>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Schema> Query<T> {
+    /// Query all records of the schema.
+    pub fn all() -> Query<T> {
+        Query {
+            cond: None,
+            order_by: vec![],
+            limit: None,
+            offset: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Query records of the schema that matches the condition.
+    pub fn filter(cond: Cond) -> Query<T> {
+        Query {
+            cond: Some(cond),
+            ..Query::all()
+        }
+    }
+
+    /// Sort the records by the field. It can be called multiple times to sort by more fields.
+    pub fn order_by(mut self, field_name: &str, order: Order) -> Query<T> {
+        self.order_by.push((field_name.to_string(), order));
+        self
+    }
+
+    /// Return at most `limit` records.
+    pub fn limit(mut self, limit: u64) -> Query<T> {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skip the first `offset` records.
+    pub fn offset(mut self, offset: u64) -> Query<T> {
+        self.offset = Some(offset);
+        self
+    }
+}
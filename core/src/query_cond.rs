@@ -1,6 +1,7 @@
 //! Yoshino query conditions
 
-use crate::Schema;
+use crate::db::DbValue;
+use crate::{Field, Schema, TimeField};
 
 /// Query conditions.
 ///
@@ -12,8 +13,69 @@ pub enum Cond {
     IsNull { field_name: String },
     /// The field is not null.
     IsNotNull { field_name: String },
+    /// The field is equal to `value`.
+    EqualTo { field_name: String, value: DbValue },
+    /// The field is not equal to `value`.
+    NotEqualTo { field_name: String, value: DbValue },
+    /// The field is greater than `value`.
+    GreaterThan { field_name: String, value: DbValue },
+    /// The field is less than `value`.
+    LessThan { field_name: String, value: DbValue },
+    /// The field is greater than or equal to `value`.
+    GreaterThanOrEqualTo { field_name: String, value: DbValue },
+    /// The field is less than or equal to `value`.
+    LessThanOrEqualTo { field_name: String, value: DbValue },
+    /// The field is equal to one of `values`.
+    In {
+        field_name: String,
+        values: Vec<DbValue>,
+    },
+    /// The field is equal to none of `values`.
+    NotIn {
+        field_name: String,
+        values: Vec<DbValue>,
+    },
+    /// The field is a float and it's equal to `value`, or within `epsilon` of `value` if
+    /// `epsilon` is given.
+    FloatEqualTo {
+        field_name: String,
+        value: f64,
+        epsilon: Option<f64>,
+    },
+    /// The field is a float and it's great than `value`.
+    FloatGreaterThan { field_name: String, value: f64 },
+    /// The field is a float and it's less than `value`.
+    FloatLessThan { field_name: String, value: f64 },
+    /// The field is a float and it's great than or equal to `value`.
+    FloatGreaterThanOrEqualTo { field_name: String, value: f64 },
+    /// The field is a float and it's less than or equal to `value`.
+    FloatLessThanOrEqualTo { field_name: String, value: f64 },
+    /// The field is a binary and it's equal to `value`.
+    BinaryEqualTo { field_name: String, value: Vec<u8> },
+    /// The field is equal to `value`, or the field is null if `value` is null.
+    NullSafeEqualTo { field_name: String, value: DbValue },
     /// The field is a text and it's equal to `value`.
     TextEqualTo { field_name: String, value: String },
+    /// The field is a text and it's not equal to `value`.
+    TextNotEqualTo { field_name: String, value: String },
+    /// The field is a text and it's lexicographically greater than `value`.
+    TextGreaterThan { field_name: String, value: String },
+    /// The field is a text and it's lexicographically less than `value`.
+    TextLessThan { field_name: String, value: String },
+    /// The field is a text and it's equal to `value` ignoring the case of ASCII letters.
+    TextEqualToIgnoreCase { field_name: String, value: String },
+    /// The field is a text and it matches the SQL LIKE `pattern`, where `%` matches any
+    /// sequence of characters and `_` matches any single character. Wildcards are escaped
+    /// with `escape` if it's given, or the default escape character of the database otherwise.
+    Like {
+        field_name: String,
+        pattern: String,
+        escape: Option<char>,
+    },
+    /// The field is a text and it matches the glob `pattern` case sensitively, where `*`
+    /// matches any sequence of characters, `?` matches any single character and `[...]`
+    /// matches one of the characters in the brackets.
+    Glob { field_name: String, pattern: String },
     /// The field is an integer and it's equal to `value`.
     IntegerEqualTo { field_name: String, value: i64 },
     /// The field is an integer and it's not equal to `value`.
@@ -26,6 +88,25 @@ pub enum Cond {
     IntegerGreaterThanOrEqualTo { field_name: String, value: i64 },
     /// The field is an integer and it's less than or equal to `value`.
     IntegerLessThanOrEqualTo { field_name: String, value: i64 },
+    /// The field is a date, time or duration and it's earlier than `value`. Date times are
+    /// compared with microsecond precision, and only in years 0 to 9999. See the `datetime`
+    /// module.
+    Before { field_name: String, value: DbValue },
+    /// The field is a date, time or duration and it's later than `value`. The same limits as
+    /// `before` apply.
+    After { field_name: String, value: DbValue },
+    /// The field is between `start` and `end`, inclusively.
+    Between {
+        field_name: String,
+        start: DbValue,
+        end: DbValue,
+    },
+    /// The field is a JSON text and the value at `path` in it is equal to `value`.
+    JsonPathEqualTo {
+        field_name: String,
+        path: String,
+        value: DbValue,
+    },
     /// Both conditions are true.
     And { left: Box<Cond>, right: Box<Cond> },
     /// At least one of the two conditions is true.
@@ -72,6 +153,24 @@ impl Cond {
         }
     }
 
+    /// The field is equal to `value`, which can be any field type such as enums derived
+    /// with `YoshinoEnum`. Use `is_null` to check whether a nullable field is null.
+    pub fn equal_to<T: Field>(field_name: &str, value: T) -> Cond {
+        Cond::EqualTo {
+            field_name: field_name.to_string(),
+            value: value.to_db_data().into(),
+        }
+    }
+
+    /// The field is not equal to `value`, which can be any field type such as enums derived
+    /// with `YoshinoEnum`. Use `is_not_null` to check whether a nullable field is not null.
+    pub fn not_equal_to<T: Field>(field_name: &str, value: T) -> Cond {
+        Cond::NotEqualTo {
+            field_name: field_name.to_string(),
+            value: value.to_db_data().into(),
+        }
+    }
+
     /// The field is an integer and it's equal to `value`.
     pub fn integer_equal_to(field_name: &str, value: i64) -> Cond {
         Cond::IntegerEqualTo {
@@ -120,6 +219,104 @@ impl Cond {
         }
     }
 
+    /// The field is an integer and it's equal to one of `values`.
+    pub fn integer_in(field_name: &str, values: &[i64]) -> Cond {
+        Cond::In {
+            field_name: field_name.to_string(),
+            values: values.iter().map(|value| DbValue::Int(*value)).collect(),
+        }
+    }
+
+    /// The field is an integer and it's equal to none of `values`.
+    pub fn integer_not_in(field_name: &str, values: &[i64]) -> Cond {
+        Cond::NotIn {
+            field_name: field_name.to_string(),
+            values: values.iter().map(|value| DbValue::Int(*value)).collect(),
+        }
+    }
+
+    /// The field is a text and it's equal to one of `values`.
+    pub fn text_in<S: AsRef<str>>(field_name: &str, values: &[S]) -> Cond {
+        Cond::In {
+            field_name: field_name.to_string(),
+            values: values.iter().map(|value| DbValue::from(value.as_ref())).collect(),
+        }
+    }
+
+    /// The field is a text and it's equal to none of `values`.
+    pub fn text_not_in<S: AsRef<str>>(field_name: &str, values: &[S]) -> Cond {
+        Cond::NotIn {
+            field_name: field_name.to_string(),
+            values: values.iter().map(|value| DbValue::from(value.as_ref())).collect(),
+        }
+    }
+
+    /// The field is a float and it's equal to `value`.
+    pub fn float_equal_to(field_name: &str, value: f64) -> Cond {
+        Cond::FloatEqualTo {
+            field_name: field_name.to_string(),
+            value,
+            epsilon: None,
+        }
+    }
+
+    /// The field is a float and it's within `epsilon` of `value`.
+    pub fn float_approx_equal_to(field_name: &str, value: f64, epsilon: f64) -> Cond {
+        Cond::FloatEqualTo {
+            field_name: field_name.to_string(),
+            value,
+            epsilon: Some(epsilon),
+        }
+    }
+
+    /// The field is a float and it's great than `value`.
+    pub fn float_great_than(field_name: &str, value: f64) -> Cond {
+        Cond::FloatGreaterThan {
+            field_name: field_name.to_string(),
+            value,
+        }
+    }
+
+    /// The field is a float and it's less than `value`.
+    pub fn float_less_than(field_name: &str, value: f64) -> Cond {
+        Cond::FloatLessThan {
+            field_name: field_name.to_string(),
+            value,
+        }
+    }
+
+    /// The field is a float and it's great than or equal to `value`.
+    pub fn float_great_than_or_equal_to(field_name: &str, value: f64) -> Cond {
+        Cond::FloatGreaterThanOrEqualTo {
+            field_name: field_name.to_string(),
+            value,
+        }
+    }
+
+    /// The field is a float and it's less than or equal to `value`.
+    pub fn float_less_than_or_equal_to(field_name: &str, value: f64) -> Cond {
+        Cond::FloatLessThanOrEqualTo {
+            field_name: field_name.to_string(),
+            value,
+        }
+    }
+
+    /// The field is a binary and it's equal to `value`.
+    pub fn binary_equal_to(field_name: &str, value: &[u8]) -> Cond {
+        Cond::BinaryEqualTo {
+            field_name: field_name.to_string(),
+            value: value.to_vec(),
+        }
+    }
+
+    /// The field is equal to `value` if it's `Some`, or the field is null if it's `None`.
+    pub fn null_safe_equal_to<T: Field>(field_name: &str, value: Option<T>) -> Cond {
+        Cond::NullSafeEqualTo {
+            field_name: field_name.to_string(),
+            value: value.to_db_data().into(),
+        }
+    }
+
     /// The field is a text and it's equal to `value`.
     pub fn text_equal_to(field_name: &str, value: &str) -> Cond {
         Cond::TextEqualTo {
@@ -128,6 +325,131 @@ impl Cond {
         }
     }
 
+    /// The field is a text and it's not equal to `value`.
+    pub fn text_not_equal_to(field_name: &str, value: &str) -> Cond {
+        Cond::TextNotEqualTo {
+            field_name: field_name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// The field is a text and it's lexicographically greater than `value`.
+    pub fn text_greater_than(field_name: &str, value: &str) -> Cond {
+        Cond::TextGreaterThan {
+            field_name: field_name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// The field is a text and it's lexicographically less than `value`.
+    pub fn text_less_than(field_name: &str, value: &str) -> Cond {
+        Cond::TextLessThan {
+            field_name: field_name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// The field is a text and it's equal to `value` ignoring the case of ASCII letters.
+    pub fn text_equal_to_ignore_case(field_name: &str, value: &str) -> Cond {
+        Cond::TextEqualToIgnoreCase {
+            field_name: field_name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// The field is a text and it matches the SQL LIKE `pattern`. Whether the letter case is
+    /// ignored depends on the database: SQLite ignores the case of ASCII letters, and MySQL
+    /// follows the collation of the column.
+    ///
+    /// The pattern is passed as is, so wildcards are escaped by the default escape character
+    /// of the database: none in SQLite, and `\` in MySQL. Use `like_with_escape` to match
+    /// wildcards literally in both.
+    pub fn like(field_name: &str, pattern: &str) -> Cond {
+        Cond::Like {
+            field_name: field_name.to_string(),
+            pattern: pattern.to_string(),
+            escape: None,
+        }
+    }
+
+    /// The field is a text and it matches the SQL LIKE `pattern`, where the wildcards escaped
+    /// by `LIKE_ESCAPE_CHAR` are matched literally. `escape_like_pattern` escapes a value.
+    pub fn like_with_escape(field_name: &str, pattern: &str) -> Cond {
+        Cond::Like {
+            field_name: field_name.to_string(),
+            pattern: pattern.to_string(),
+            escape: Some(LIKE_ESCAPE_CHAR),
+        }
+    }
+
+    /// The field is a text and it matches the glob `pattern` case sensitively.
+    pub fn glob(field_name: &str, pattern: &str) -> Cond {
+        Cond::Glob {
+            field_name: field_name.to_string(),
+            pattern: pattern.to_string(),
+        }
+    }
+
+    /// The field is a text and it starts with `value`. It's a LIKE condition, so the letter
+    /// case may be ignored as `like` does.
+    pub fn starts_with(field_name: &str, value: &str) -> Cond {
+        Cond::like_with_escape(field_name, &format!("{}%", escape_like_pattern(value)))
+    }
+
+    /// The field is a text and it ends with `value`. It's a LIKE condition, so the letter
+    /// case may be ignored as `like` does.
+    pub fn ends_with(field_name: &str, value: &str) -> Cond {
+        Cond::like_with_escape(field_name, &format!("%{}", escape_like_pattern(value)))
+    }
+
+    /// The field is a text and it contains `value`. It's a LIKE condition, so the letter
+    /// case may be ignored as `like` does.
+    pub fn contains(field_name: &str, value: &str) -> Cond {
+        Cond::like_with_escape(field_name, &format!("%{}%", escape_like_pattern(value)))
+    }
+
+    /// The field is a date, time or duration and it's earlier than `value`. Date times are
+    /// compared with microsecond precision, and only in years 0 to 9999. See the `datetime`
+    /// module.
+    pub fn before<T: TimeField>(field_name: &str, value: T) -> Cond {
+        Cond::Before {
+            field_name: field_name.to_string(),
+            value: value.to_db_data().into(),
+        }
+    }
+
+    /// The field is a date, time or duration and it's later than `value`. The same limits as
+    /// `before` apply.
+    pub fn after<T: TimeField>(field_name: &str, value: T) -> Cond {
+        Cond::After {
+            field_name: field_name.to_string(),
+            value: value.to_db_data().into(),
+        }
+    }
+
+    /// The field is between `start` and `end`, inclusively. It works for any ordered field
+    /// types, such as integers, floats, texts and date times.
+    pub fn between<T: Field>(field_name: &str, start: T, end: T) -> Cond {
+        Cond::Between {
+            field_name: field_name.to_string(),
+            start: start.to_db_data().into(),
+            end: end.to_db_data().into(),
+        }
+    }
+
+    /// The field is a JSON text and the value at `path` in it is equal to `value`.
+    ///
+    /// `path` is a JSON path like `$.a.b[0]`, which is supported by both SQLite and MySQL.
+    /// Only scalars are compared: a JSON string matches a text value, a JSON number matches an
+    /// integer or float value, and a JSON boolean matches 1 or 0. A JSON null matches nothing.
+    pub fn json_path_equal_to<V: Into<DbValue>>(field_name: &str, path: &str, value: V) -> Cond {
+        Cond::JsonPathEqualTo {
+            field_name: field_name.to_string(),
+            path: path.to_string(),
+            value: value.into(),
+        }
+    }
+
     /// Get the condition that the row id of record is equal to the given `record`.
     /// Return None if the given record doesn't have a row id field or the field is new.
     pub fn is_row_id_equal_to<T: Schema>(record: &T) -> Option<Cond> {
@@ -143,6 +465,38 @@ impl Cond {
             None => None,
         }
     }
+
+    /// Get the condition that the primary key (RowID or UuidKey) of record is equal to the
+    /// given `record`. Return None if the given record doesn't have a primary key field or the
+    /// key is new.
+    pub fn is_primary_key_equal_to<T: Schema>(record: &T) -> Option<Cond> {
+        let field_name = T::get_primary_key_field_name()?;
+        let index = T::get_fields()
+            .iter()
+            .position(|(name, _)| *name == field_name)?;
+        match record.get_values().swap_remove(index) {
+            DbValue::Null => None,
+            value => Some(Cond::EqualTo { field_name, value }),
+        }
+    }
+}
+
+/// The escape character of the LIKE patterns built by Yoshino. Adaptors declare it with an
+/// explicit `ESCAPE` clause, because the default escape character `\` of MySQL is disabled by
+/// the `NO_BACKSLASH_ESCAPES` SQL mode and SQLite has no default escape character.
+pub const LIKE_ESCAPE_CHAR: char = '!';
+
+/// Escape the wildcards `%` and `_` and the escape character `LIKE_ESCAPE_CHAR` in `value`, so
+/// it can be matched literally in a LIKE pattern.
+pub fn escape_like_pattern(value: &str) -> String {
+    let mut pattern = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | LIKE_ESCAPE_CHAR) {
+            pattern.push(LIKE_ESCAPE_CHAR);
+        }
+        pattern.push(c);
+    }
+    pattern
 }
 
 impl std::ops::BitOr<Cond> for Cond {
@@ -1,176 +1,227 @@
 //! Data type declarations for Yoshino.
 //!
-//! Only the types that implement a trait of "fields" can be used in Yoshino
-//! schema struct. Now available field traits are:
+//! Only the types that implement the `Field` trait can be used in Yoshino schema struct.
+//! Now available field types are:
 //!
-//! * `TextField` - nonnull text field.
-//! * `NullableTextField` - nullable text field.
-//! * `IntegerField` - nonnull 64-bit integer field.
-//! * `NullableIntegerField` - nullable 64-bit integer field.
-//! * `FloatField` - nonnull 64-bit floating point field.
+//! * `String` - text field.
+//! * `i64` - 64-bit integer field.
+//! * `i8`, `i16`, `i32`, `u8`, `u16`, `u32` - narrower integer fields. Values read from the
+//!   database are checked against the range of the type.
+//! * `bool` - boolean field, stored as 0 or 1.
+//! * `f64` - 64-bit floating point field.
+//! * `f32` - 32-bit floating point field.
+//! * `Vec<u8>` - binary large object field.
+//! * `RowID` - auto increment row ID field.
 //!
-//! If you want to use a custom type in schema struct, you need to implement
-//! one field trait for this custom type.
+//! * `DateTime<Utc>`, `NaiveDateTime`, `NaiveDate`, `Duration` - date and time fields from
+//!   `chrono`, available with the `chrono` feature.
+//! * `Uuid` - UUID field from `uuid`, available with the `uuid` feature.
+//! * `UuidKey` - UUID primary key field, available with the `uuid` feature.
+//! * `Json<T>` - field of any serde serializable value stored as a JSON text, available with
+//!   the `json` feature.
 //!
-//! All field traits declare the method to generate DbData object that can be
-//! accepted by the Yoshino database interfaces.
-
-use crate::db::{DbData, DbDataType};
-
-/// It can be serialized as a String in Yoshino.
-pub trait TextField: Sized {
-    /// Create an instance from a boxed DbData trait object.
-    fn from_db_data(data: &Box<dyn DbData>) -> Self;
-    /// Create the string to be used by the Yoshino.
-    fn to_db_data(&self) -> String;
-    /// The `DbDataType` of this field. For all `TextField` objects, it's `DbDataType::Text`.
-    fn db_field_type() -> DbDataType {
-        DbDataType::Text
-    }
+//! Every field type `T` can also be used as `Option<T>`, which makes the field nullable.
+//!
+//! If you want to use a custom type in schema struct, you need to implement `Field` for
+//! this custom type. `Field` declares the methods to convert the field from / to `DbValue`
+//! objects that can be accepted by the Yoshino database interfaces.
+
+use crate::db::{DbDataType, DbError, DbErrorKind, DbValue};
+
+/// A type that can be stored as a field of a Yoshino schema struct.
+pub trait Field: Sized {
+    /// The type this field is converted to before being sent to the database.
+    type Storage: Into<DbValue>;
+    /// Create an instance from a `DbValue` read from the database. Return an error if the
+    /// value can't be decoded.
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError>;
+    /// Create the value to be used by the Yoshino.
+    fn to_db_data(&self) -> Self::Storage;
+    /// The `DbDataType` of this field.
+    fn db_field_type() -> DbDataType;
+    /// Generate the value of a key field that is generated when the record is inserted, like
+    /// `UuidKey::NewV4`. Other fields do nothing.
+    fn generate_key(&mut self) {}
+    /// Assign the row id given by the database to a RowID field. Other fields do nothing.
+    fn assign_row_id(&mut self, _row_id: RowID) {}
 }
 
-/// It can be serialized as a nullable String in Yoshino.
-pub trait NullableTextField: Sized {
-    /// Create an instance from a boxed DbData trait object.
-    fn from_db_data(data: &Box<dyn DbData>) -> Self;
-    /// Create the string to be used by the Yoshino.
-    fn to_db_data(&self) -> Option<String>;
-    /// The `DbDataType` of this field. For all `NullableTextField` objects, it's `DbDataType::NullableText`.
+impl Field for String {
+    type Storage = String;
+    fn from_db_data(data: &DbValue) -> Result<String, DbError> {
+        match data {
+            DbValue::Text(s) => Ok(s.clone()),
+            _ => Err(get_type_mismatch_error("a text value", data)),
+        }
+    }
+    fn to_db_data(&self) -> String {
+        self.to_owned()
+    }
     fn db_field_type() -> DbDataType {
-        DbDataType::NullableText
+        DbDataType::Text
     }
 }
 
-/// It can be serailized as a 64-bit integer in Yoshino.
-pub trait IntegerField: Sized {
-    /// Create an instance from a boxed DbData trait object.
-    fn from_db_data(data: &Box<dyn DbData>) -> Self;
-    /// Create the i64 to be used by the Yoshino.
-    fn to_db_data(&self) -> i64;
-    /// The `DbDataType` of this field. For all `IntegerField` objects, it's `DbDataType::Int`.
+impl Field for i64 {
+    type Storage = i64;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        match data {
+            DbValue::Int(v) => Ok(*v),
+            _ => Err(get_type_mismatch_error("an integer value", data)),
+        }
+    }
+    fn to_db_data(&self) -> i64 {
+        *self
+    }
     fn db_field_type() -> DbDataType {
         DbDataType::Int
     }
 }
 
-/// It can be serailized as a nullable 64-bit integer in Yoshino.
-pub trait NullableIntegerField: Sized {
-    /// Create an instance from a boxed DbData trait object.
-    fn from_db_data(data: &Box<dyn DbData>) -> Self;
-    /// Create the i64 to be used by the Yoshino.
-    fn to_db_data(&self) -> Option<i64>;
-    /// The `DbDataType` of this field. For all `IntegerField` objects, it's `DbDataType::NullableInt`.
-    fn db_field_type() -> DbDataType {
-        DbDataType::NullableInt
+impl Field for f64 {
+    type Storage = f64;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        match data {
+            DbValue::Float(v) => Ok(*v),
+            DbValue::Int(v) => Ok(*v as f64),
+            _ => Err(get_type_mismatch_error("a float value", data)),
+        }
+    }
+    fn to_db_data(&self) -> f64 {
+        *self
     }
-}
-
-/// It can be serailized as 64-bit floating point numeric number in Yoshino.
-pub trait FloatField: Sized {
-    /// Create an instance from a boxed DbData trait object.
-    fn from_db_data(data: &Box<dyn DbData>) -> Self;
-    /// Create the f64 to be used by the Yoshino.
-    fn to_db_data(&self) -> f64;
-    /// The `DbDataType` of this field. For all `FloatField` objects, it's `DbDataType::Float`.
     fn db_field_type() -> DbDataType {
         DbDataType::Float
     }
 }
 
-/// A binary large object field for storing raw data.
-pub trait BinaryField: Sized {
-    /// Create an instance from a boxed DbData trait object.
-    fn from_db_data(data: &Box<dyn DbData>) -> Self;
-    /// Create the Vec<u8> to be used by the Yoshino.
-    fn to_db_data(&self)-> Vec<u8>;
-    /// The `DbDataType` of this field. For all `BinaryField` objects, it's `DbDataType::Binary`.
+impl Field for Vec<u8> {
+    type Storage = Vec<u8>;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        match data {
+            DbValue::Blob(v) => Ok(v.clone()),
+            _ => Err(get_type_mismatch_error("a binary value", data)),
+        }
+    }
+    fn to_db_data(&self) -> Vec<u8> {
+        self.clone()
+    }
     fn db_field_type() -> DbDataType {
         DbDataType::Binary
     }
 }
 
-/// A nullable binary large object field for storing raw data.
-pub trait NullableBinaryField: Sized {
-    /// Create an instance from a boxed DbData trait object.
-    fn from_db_data(data: &Box<dyn DbData>) -> Self;
-    /// Create the Vec<u8> to be used by the Yoshino.
-    fn to_db_data(&self)-> Option<Vec<u8>>;
-    /// The `DbDataType` of this field. For all `NullableBinaryField` objects, it's `DbDataType::NullableBinary`.
-    fn db_field_type() -> DbDataType {
-        DbDataType::NullableBinary
+impl Field for bool {
+    type Storage = i64;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        match data {
+            DbValue::Int(0) => Ok(false),
+            DbValue::Int(1) => Ok(true),
+            _ => Err(get_type_mismatch_error("a boolean value", data)),
+        }
     }
-}
-
-impl TextField for String {
-    fn from_db_data(data: &Box<dyn DbData>) -> String {
-        <String as DbData>::from_boxed_db_data(data)
+    fn to_db_data(&self) -> i64 {
+        *self as i64
     }
-    fn to_db_data(&self) -> String {
-        self.to_owned()
+    fn db_field_type() -> DbDataType {
+        DbDataType::Bool
     }
 }
 
-impl NullableTextField for Option<String> {
-    fn from_db_data(data: &Box<dyn DbData>) -> Option<String> {
-        <Option<String> as DbData>::from_boxed_db_data(data)
-    }
-    fn to_db_data(&self) -> Option<String> {
-        match self {
-            None => None,
-            Some(x) => Some(x.to_owned()),
+/// Implement `Field` for an integer type narrower than `i64`. Values out of its range are
+/// rejected when decoding.
+macro_rules! impl_integer_field {
+    ($t: ty, $db_data_type: expr) => {
+        impl Field for $t {
+            type Storage = i64;
+            fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+                match data {
+                    DbValue::Int(v) => <$t>::try_from(*v)
+                        .map_err(|_| get_out_of_range_error(stringify!($t), data)),
+                    _ => Err(get_type_mismatch_error("an integer value", data)),
+                }
+            }
+            fn to_db_data(&self) -> i64 {
+                *self as i64
+            }
+            fn db_field_type() -> DbDataType {
+                $db_data_type
+            }
         }
-    }
+    };
 }
 
-impl IntegerField for i64 {
-    fn from_db_data(data: &Box<dyn DbData>) -> Self {
-        <i64 as DbData>::from_boxed_db_data(data)
+impl_integer_field!(i8, DbDataType::Int8);
+impl_integer_field!(i16, DbDataType::Int16);
+impl_integer_field!(i32, DbDataType::Int32);
+impl_integer_field!(u8, DbDataType::UInt8);
+impl_integer_field!(u16, DbDataType::UInt16);
+impl_integer_field!(u32, DbDataType::UInt32);
+
+impl Field for f32 {
+    type Storage = f64;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        let v = f64::from_db_data(data)?;
+        if v.is_finite() && v.abs() > f32::MAX as f64 {
+            return Err(get_out_of_range_error("f32", data));
+        }
+        Ok(v as f32)
     }
-    fn to_db_data(&self) -> i64 {
-        *self
+    fn to_db_data(&self) -> f64 {
+        *self as f64
+    }
+    fn db_field_type() -> DbDataType {
+        DbDataType::Float32
     }
 }
 
-impl NullableIntegerField for Option<i64> {
-    fn from_db_data(data: &Box<dyn DbData>) -> Self {
-        <Option<i64> as DbData>::from_boxed_db_data(data)
+/// Any field can be made nullable by wrapping it in an `Option`, which is stored as `NULL`
+/// when it's `None`.
+impl<T: Field> Field for Option<T> {
+    type Storage = Option<T::Storage>;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        match data {
+            DbValue::Null => Ok(None),
+            _ => T::from_db_data(data).map(Some),
+        }
     }
-    fn to_db_data(&self) -> Option<i64> {
-        *self
+    fn to_db_data(&self) -> Option<T::Storage> {
+        self.as_ref().map(T::to_db_data)
     }
-}
-
-impl FloatField for f64 {
-    fn from_db_data(data: &Box<dyn DbData>) -> Self {
-        <f64 as DbData>::from_boxed_db_data(data)
+    fn db_field_type() -> DbDataType {
+        T::db_field_type().nullable()
     }
-    fn to_db_data(&self) -> f64 {
-        *self
+    fn generate_key(&mut self) {
+        if let Some(value) = self {
+            value.generate_key();
+        }
     }
 }
 
-impl BinaryField for Vec<u8> {
-    fn from_db_data(data: &Box<dyn DbData>) -> Self {
-        <Vec<u8> as DbData>::from_boxed_db_data(data)
-    }
-    fn to_db_data(&self)-> Vec<u8> {
-        self.clone()
-    }
+/// A field of date, time or duration that can be compared with `Cond::before` and
+/// `Cond::after`.
+pub trait TimeField: Field {}
+
+/// Create the error for a value that doesn't match the field type.
+pub fn get_type_mismatch_error(expected: &str, data: &DbValue) -> DbError {
+    DbError::new(
+        DbErrorKind::TypeMismatch,
+        format!("Expected {}, found {:?}", expected, data),
+    )
 }
 
-impl NullableBinaryField for Option<Vec<u8>> {
-    fn from_db_data(data: &Box<dyn DbData>) -> Self {
-        <Option<Vec<u8>> as DbData>::from_boxed_db_data(data)
-    }
-    fn to_db_data(&self)-> Option<Vec<u8>> {
-        self.clone()
-    }
+/// Create the error for a value that is out of the range of the field type.
+fn get_out_of_range_error(type_name: &str, data: &DbValue) -> DbError {
+    DbError::new(
+        DbErrorKind::TypeMismatch,
+        format!("{:?} is out of the range of {}", data, type_name),
+    )
 }
 
 /// Auto increment row ID field. It will be represented as an integer primary key.
 ///
 /// A schema can has at most one RowID field.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RowID {
     /// A new created object so it doesn't have a row id yet.
     NEW,
@@ -178,16 +229,24 @@ pub enum RowID {
     ID(i64),
 }
 
-impl RowID {
-    pub fn from_db_data(data: &Box<dyn DbData>) -> RowID {
-        <RowID as DbData>::from_boxed_db_data(data)
+impl Field for RowID {
+    type Storage = RowID;
+    fn from_db_data(data: &DbValue) -> Result<RowID, DbError> {
+        match data {
+            DbValue::Null => Ok(RowID::NEW),
+            DbValue::Int(v) => Ok(RowID::ID(*v)),
+            _ => Err(get_type_mismatch_error("an integer row id", data)),
+        }
     }
-    pub fn to_db_data(&self) -> RowID {
-        self.clone()
+    fn to_db_data(&self) -> RowID {
+        *self
     }
-    pub fn db_field_type() -> DbDataType {
+    fn db_field_type() -> DbDataType {
         DbDataType::RowID
     }
+    fn assign_row_id(&mut self, row_id: RowID) {
+        *self = row_id;
+    }
 }
 
 /// Make the type a data schema in the relational database.
@@ -198,10 +257,64 @@ pub trait Schema: 'static {
     fn get_schema_name() -> String;
     /// the list of field names and types of this data struct
     fn get_fields() -> Vec<(String, DbDataType)>;
-    /// the values of all fields in boxed DbData objects.
-    fn get_values(&self) -> Vec<Box<dyn DbData>>;
-    /// to create the struct with valeus of all fields in boxed DbData objects
-    fn create_with_values(values: Vec<Box<dyn DbData>>) -> Self;
+    /// the values of all fields in `DbValue` objects.
+    fn get_values(&self) -> Vec<DbValue>;
+    /// to create the struct with valeus of all fields in `DbValue` objects. Return an error
+    /// naming the schema and the field if a value can't be decoded.
+    fn create_with_values(values: Vec<DbValue>) -> Result<Self, DbError>
+    where
+        Self: Sized;
+
+    /// generate the values of the key fields that are generated when the record is inserted,
+    /// like `UuidKey::NewV4`. The adaptors call it before inserting the record.
+    fn generate_keys(&mut self) {}
+
+    /// the list of field names in the unique key of this schema. Empty if there is no unique key.
+    fn get_unique_key() -> Vec<String> {
+        vec![]
+    }
+
+    /// get the name of the RowID field. Return `None` if there is no such field.
+    fn get_row_id_field_name() -> Option<String> {
+        Self::get_fields()
+            .into_iter()
+            .find(|(_, field_type)| *field_type == DbDataType::RowID)
+            .map(|(name, _)| name)
+    }
+
+    /// get the name of the primary key field, which is either a RowID field or a UuidKey
+    /// field. Return `None` if there is no such field.
+    fn get_primary_key_field_name() -> Option<String> {
+        Self::get_fields()
+            .into_iter()
+            .find(|(_, field_type)| {
+                matches!(field_type, DbDataType::RowID | DbDataType::UuidKey)
+            })
+            .map(|(name, _)| name)
+    }
+
+    /// get the fields to identify an existing record when upserting a record, which is the
+    /// unique key if there is one, or the primary key field otherwise.
+    /// Return `None` if there is neither of them.
+    fn get_upsert_key() -> Option<Vec<String>> {
+        let unique_key = Self::get_unique_key();
+        if unique_key.is_empty() {
+            Self::get_primary_key_field_name().map(|field_name| vec![field_name])
+        } else {
+            Some(unique_key)
+        }
+    }
+
+    /// get the type of the field. Return `None` if there is no such field.
+    fn get_field_type(field_name: &str) -> Option<DbDataType> {
+        Self::get_fields()
+            .into_iter()
+            .find(|(name, _)| name == field_name)
+            .map(|(_, field_type)| field_type)
+    }
+
+    /// set the value of the RowID field. Do nothing if there is no such field.
+    fn set_row_id(&mut self, _row_id: RowID) {}
 
     /// get the name and value of the RowID field.
     /// Return `None` if there is no such field. Panic if there are more than one RowID field.
@@ -215,7 +328,8 @@ pub trait Schema: 'static {
                 if answer.is_none() {
                     let field_value = values.get(i).unwrap();
                     let answer_field_name = field_name.to_owned();
-                    let answer_field_value = RowID::from_db_data(field_value);
+                    // the value is created by `RowID::to_db_data`, so it can always be decoded
+                    let answer_field_value = RowID::from_db_data(field_value).unwrap();
                     answer = Some((answer_field_name, answer_field_value));
                 } else {
                     panic!(
@@ -0,0 +1,82 @@
+//! UUID field types, available with the `uuid` feature.
+//!
+//! UUIDs are stored as 16-byte binary values.
+
+use crate::db::{DbDataType, DbError, DbValue};
+use crate::types::{get_type_mismatch_error, Field};
+use uuid::Uuid;
+
+impl Field for Uuid {
+    type Storage = Vec<u8>;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        match data {
+            DbValue::Blob(v) => {
+                Uuid::from_slice(v).map_err(|_| get_type_mismatch_error("a 16-byte UUID", data))
+            }
+            _ => Err(get_type_mismatch_error("a 16-byte UUID", data)),
+        }
+    }
+    fn to_db_data(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+    fn db_field_type() -> DbDataType {
+        DbDataType::Uuid
+    }
+}
+
+/// UUID primary key field. It can be used instead of `RowID` when the records need globally
+/// unique ids.
+///
+/// The key of a new record is generated when the record is inserted. `DbAdaptor::save` writes
+/// the generated key back to the record, or use `generate` before inserting the record to know
+/// its key. A schema can has at most one UuidKey field, and it shouldn't have a RowID field at
+/// the same time. An `Option<UuidKey>` field is not a key but a nullable UUID field, whose
+/// `NewV4` or `NewV7` value is still generated at insert time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UuidKey {
+    /// A new created object whose key will be generated as a random UUID (version 4).
+    NewV4,
+    /// A new created object whose key will be generated as a time-ordered UUID (version 7).
+    NewV7,
+    /// The key of the object.
+    ID(Uuid),
+}
+
+impl UuidKey {
+    /// Generate the key if it's not generated yet, and return it.
+    pub fn generate(&mut self) -> Uuid {
+        let id = match self {
+            UuidKey::NewV4 => Uuid::new_v4(),
+            UuidKey::NewV7 => Uuid::now_v7(),
+            UuidKey::ID(id) => *id,
+        };
+        *self = UuidKey::ID(id);
+        id
+    }
+}
+
+impl From<UuidKey> for DbValue {
+    /// A key that is not generated yet is converted to null.
+    fn from(value: UuidKey) -> DbValue {
+        match value {
+            UuidKey::NewV4 | UuidKey::NewV7 => DbValue::Null,
+            UuidKey::ID(id) => DbValue::Blob(id.to_db_data()),
+        }
+    }
+}
+
+impl Field for UuidKey {
+    type Storage = UuidKey;
+    fn from_db_data(data: &DbValue) -> Result<Self, DbError> {
+        Uuid::from_db_data(data).map(UuidKey::ID)
+    }
+    fn to_db_data(&self) -> UuidKey {
+        *self
+    }
+    fn db_field_type() -> DbDataType {
+        DbDataType::UuidKey
+    }
+    fn generate_key(&mut self) {
+        self.generate();
+    }
+}
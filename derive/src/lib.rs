@@ -7,12 +7,24 @@ use proc_macro::TokenStream;
 use proc_macro::TokenTree;
 use proc_macro::TokenTree::{Group, Ident, Punct};
 
+/// Field of a schema struct.
+struct SchemaField {
+    name: String,
+    field_type: String,
+    /// attributes declared with `#[yoshino(...)]`
+    attributes: Vec<String>,
+}
+
 /// Derive macro for implementing `yoshino_core::Schema` trait.
-#[proc_macro_derive(Schema)]
+///
+/// Fields can be annotated with `#[yoshino(unique)]` to make them part of the unique key
+/// of the schema.
+#[proc_macro_derive(Schema, attributes(yoshino))]
 pub fn derive_schema_fn(src: TokenStream) -> TokenStream {
     let mut src_tokens = src.into_iter();
     // get struct name
-    let struct_name = get_next_struct_name(&mut src_tokens).unwrap().to_string();
+    let (visibility, struct_name) = get_next_struct_visibility_and_name(&mut src_tokens);
+    let struct_name = struct_name.unwrap().to_string();
     let mut derived_code = String::new();
     for it in src_tokens {
         match it {
@@ -27,17 +39,33 @@ pub fn derive_schema_fn(src: TokenStream) -> TokenStream {
     fn get_fields() -> Vec<(String, yoshino_core::db::DbDataType)> {{
         {}
     }}
-    fn get_values(&self) -> Vec<Box<dyn yoshino_core::db::DbData>> {{
+    fn get_values(&self) -> Vec<yoshino_core::db::DbValue> {{
         {}
     }}
-    fn create_with_values(values: Vec<Box<dyn yoshino_core::db::DbData>>) -> {struct_name} {{
+    fn create_with_values(
+        values: Vec<yoshino_core::db::DbValue>,
+    ) -> Result<{struct_name}, yoshino_core::db::DbError> {{
         {}
     }}
-}}",
+    fn get_unique_key() -> Vec<String> {{
+        {}
+    }}
+    fn generate_keys(&mut self) {{
+        {}
+    }}
+    fn set_row_id(&mut self, row_id: yoshino_core::RowID) {{
+        {}
+    }}
+}}
+{}",
                         struct_name.to_lowercase(),
                         get_fields_vec_code(&fields),
                         get_values_vec_code(&fields),
-                        get_create_with_values_code(&struct_name, &fields)
+                        get_create_with_values_code(&struct_name, &fields),
+                        get_unique_key_code(&fields),
+                        get_generate_keys_code(&fields),
+                        get_set_row_id_code(&fields),
+                        get_columns_code(&visibility, &struct_name, &fields)
                     );
                 } else {
                     panic!("Only StructStruct can be derived as schemas.")
@@ -51,27 +79,75 @@ pub fn derive_schema_fn(src: TokenStream) -> TokenStream {
     derived_code.parse().unwrap()
 }
 
-fn get_next_struct_name(src_iter: &mut IntoIter) -> Option<TokenTree> {
+/// Derive macro for implementing `yoshino_core::Field` trait for fieldless enums, so they
+/// can be used as fields of schema structs and compared in conditions.
+///
+/// The enum is stored as the variant name in a text field by default. Annotate the enum
+/// with `#[yoshino(integer)]` to store it as the discriminant in an integer field instead.
+#[proc_macro_derive(YoshinoEnum, attributes(yoshino))]
+pub fn derive_yoshino_enum_fn(src: TokenStream) -> TokenStream {
+    let mut src_tokens = src.into_iter();
+    let mut attributes = Vec::new();
+    let mut enum_name = None;
+    let mut variants = Vec::new();
+    while let Some(it) = src_tokens.next() {
+        match it {
+            Group(g) if g.delimiter() == proc_macro::Delimiter::Bracket => {
+                attributes.extend(get_yoshino_attributes(g.stream()));
+            }
+            Group(g) if g.delimiter() == proc_macro::Delimiter::Brace => {
+                variants = get_enum_variants_from_stream(g.stream());
+            }
+            Ident(ident) => match ident.to_string().as_str() {
+                "enum" => enum_name = src_tokens.next().map(|name| name.to_string()),
+                "struct" | "union" => panic!("Only enums can be derived as YoshinoEnum."),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    let enum_name = enum_name.unwrap();
+    if variants.is_empty() {
+        panic!("{} has no variant to be stored.", enum_name);
+    }
+    let derived_code = if attributes.iter().any(|x| x == "integer") {
+        get_integer_enum_field_code(&enum_name, &variants)
+    } else {
+        get_text_enum_field_code(&enum_name, &variants)
+    };
+    derived_code.parse().unwrap()
+}
+
+/// Get the visibility, like `pub` or `pub(crate)`, and the name of the next struct.
+fn get_next_struct_visibility_and_name(src_iter: &mut IntoIter) -> (String, Option<TokenTree>) {
+    let mut visibility = String::new();
     loop {
         let token = src_iter.next();
         match token {
             None => break,
-            Some(Ident(ident)) => {
-                if ident.to_string() == "struct" {
-                    return src_iter.next();
-                }
+            Some(Ident(ident)) => match ident.to_string().as_str() {
+                "struct" => return (visibility, src_iter.next()),
+                "pub" => visibility = "pub".to_owned(),
+                _ => {}
+            },
+            Some(Group(g))
+                if g.delimiter() == proc_macro::Delimiter::Parenthesis && visibility == "pub" =>
+            {
+                visibility = format!("pub({})", g.stream());
             }
             _ => {}
         }
     }
-    None
+    (visibility, None)
 }
 
-fn get_struct_fields_from_stream(src: TokenStream) -> Vec<(String, String)> {
+fn get_struct_fields_from_stream(src: TokenStream) -> Vec<SchemaField> {
     let mut fields = Vec::new();
     let mut state = 0;
     let mut current_field_name = String::new();
     let mut current_field_type = String::new();
+    let mut current_field_attributes = Vec::new();
+    let mut angle_bracket_depth = 0;
 
     for it in src.into_iter() {
         match state {
@@ -84,6 +160,9 @@ fn get_struct_fields_from_stream(src: TokenStream) -> Vec<(String, String)> {
                             state = 1;
                         }
                     }
+                    Group(g) if g.delimiter() == proc_macro::Delimiter::Bracket => {
+                        current_field_attributes.extend(get_yoshino_attributes(g.stream()));
+                    }
                     _ => {
                         //ignore
                     }
@@ -101,29 +180,39 @@ fn get_struct_fields_from_stream(src: TokenStream) -> Vec<(String, String)> {
                 }
             }
             2 => {
-                // wait for field type
+                // wait for field type, which ends with a ',' outside of angle brackets
                 match &it {
-                    Ident(ident) => {
-                        current_field_type = current_field_type + &ident.to_string();
+                    Punct(punct) if punct.as_char() == ',' && angle_bracket_depth == 0 => {
+                        fields.push(SchemaField {
+                            name: current_field_name.to_owned(),
+                            field_type: current_field_type.trim_end().to_owned(),
+                            attributes: current_field_attributes,
+                        });
+                        current_field_name = String::new();
+                        current_field_type = String::new();
+                        current_field_attributes = Vec::new();
+                        state = 0;
                     }
-                    Punct(punct) => match punct.as_char() {
-                        ',' => {
-                            fields.push((
-                                current_field_name.to_owned(),
-                                current_field_type.to_owned(),
-                            ));
-                            current_field_name = String::new();
-                            current_field_type = String::new();
-                            state = 0;
-                        }
-                        '<' => {
-                            current_field_type = current_field_type + "::<";
+                    Punct(punct) => {
+                        match punct.as_char() {
+                            '<' => angle_bracket_depth += 1,
+                            // the '>' of "->" is not an angle bracket
+                            '>' if !current_field_type.ends_with('-') => {
+                                angle_bracket_depth -= 1
+                            }
+                            _ => {}
                         }
-                        c => {
-                            current_field_type = current_field_type + &c.to_string();
+                        current_field_type.push(punct.as_char());
+                        // keep joint puncts like "::" together
+                        if punct.spacing() == proc_macro::Spacing::Alone {
+                            current_field_type.push(' ');
                         }
-                    },
-                    _ => {}
+                    }
+                    // identifiers, literals and groups like tuples and arrays are kept as is
+                    _ => {
+                        current_field_type.push_str(&it.to_string());
+                        current_field_type.push(' ');
+                    }
                 }
             }
             _ => {}
@@ -132,20 +221,46 @@ fn get_struct_fields_from_stream(src: TokenStream) -> Vec<(String, String)> {
 
     // end with state 2 -> there is a last field without ',' in the end
     if state == 2 {
-        fields.push((current_field_name.to_owned(), current_field_type.to_owned()));
+        fields.push(SchemaField {
+            name: current_field_name.to_owned(),
+            field_type: current_field_type.trim_end().to_owned(),
+            attributes: current_field_attributes,
+        });
     }
     fields
 }
 
-fn get_fields_vec_code(fields: &Vec<(String, String)>) -> String {
+/// Get the attributes in `yoshino(...)` from the tokens inside an attribute `#[...]`.
+fn get_yoshino_attributes(src: TokenStream) -> Vec<String> {
+    let mut attributes = Vec::new();
+    let mut tokens = src.into_iter();
+    if let Some(Ident(ident)) = tokens.next() {
+        if ident.to_string() == "yoshino" {
+            if let Some(Group(g)) = tokens.next() {
+                for it in g.stream() {
+                    if let Ident(attribute) = it {
+                        attributes.push(attribute.to_string());
+                    }
+                }
+            }
+        }
+    }
+    attributes
+}
+
+fn get_fields_vec_code(fields: &Vec<SchemaField>) -> String {
     let mut s = "vec![".to_owned();
     for i in 0..fields.len() {
         if i != 0 {
             s = s + ", ";
         }
-        let (field_name, field_type) = fields.get(i).unwrap();
+        let SchemaField {
+            name: field_name,
+            field_type,
+            ..
+        } = fields.get(i).unwrap();
         s = s + format!(
-            "(\"{}\".to_string(), {}::db_field_type())",
+            "(\"{}\".to_string(), <{} as yoshino_core::Field>::db_field_type())",
             field_name, field_type
         )
         .as_ref();
@@ -154,32 +269,234 @@ fn get_fields_vec_code(fields: &Vec<(String, String)>) -> String {
     return s;
 }
 
-fn get_values_vec_code(fields: &Vec<(String, String)>) -> String {
+fn get_values_vec_code(fields: &Vec<SchemaField>) -> String {
     let mut s = "vec![".to_string();
     for i in 0..fields.len() {
         if i != 0 {
             s = s + ", ";
         }
-        let (field_name, _) = fields.get(i).unwrap();
-        s = s + format!("Box::new(self.{}.to_db_data())", field_name).as_ref();
+        let SchemaField {
+            name: field_name,
+            field_type,
+            ..
+        } = fields.get(i).unwrap();
+        s = s + format!(
+            "<{} as yoshino_core::Field>::to_db_data(&self.{}).into()",
+            field_type, field_name
+        )
+        .as_ref();
     }
     s = s + "]";
     s
 }
 
-fn get_create_with_values_code(struct_name: &str, fields: &Vec<(String, String)>) -> String {
-    let mut s = struct_name.to_owned() + "{";
+fn get_create_with_values_code(struct_name: &str, fields: &Vec<SchemaField>) -> String {
+    let mut s = "Ok(".to_owned() + struct_name + "{";
     for i in 0..fields.len() {
         if i != 0 {
             s = s + ", ";
         }
-        let (field_name, field_type) = fields.get(i).unwrap();
+        let SchemaField {
+            name: field_name,
+            field_type,
+            ..
+        } = fields.get(i).unwrap();
         s = s + format!(
-            "{}: {}::from_db_data(&values[{}])",
-            field_name, field_type, i
+            "{}: <{} as yoshino_core::Field>::from_db_data(&values[{}])
+                .map_err(|e| e.in_field(&Self::get_schema_name(), \"{}\"))?",
+            field_name, field_type, i, field_name
         )
         .as_ref();
     }
-    s = s + "}";
+    s = s + "})";
     s
 }
+
+fn get_unique_key_code(fields: &[SchemaField]) -> String {
+    let unique_fields: Vec<String> = fields
+        .iter()
+        .filter(|field| field.attributes.iter().any(|x| x == "unique"))
+        .map(|field| format!("\"{}\".to_string()", field.name))
+        .collect();
+    format!("vec![{}]", unique_fields.join(", "))
+}
+
+fn get_generate_keys_code(fields: &[SchemaField]) -> String {
+    let statements: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            format!(
+                "<{} as yoshino_core::Field>::generate_key(&mut self.{});",
+                field.field_type, field.name
+            )
+        })
+        .collect();
+    statements.join("\n        ")
+}
+
+fn get_set_row_id_code(fields: &[SchemaField]) -> String {
+    let statements: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            format!(
+                "<{} as yoshino_core::Field>::assign_row_id(&mut self.{}, row_id);",
+                field.field_type, field.name
+            )
+        })
+        .collect();
+    statements.join("\n        ")
+}
+
+/// Get the variant names of a fieldless enum from the tokens inside its braces.
+fn get_enum_variants_from_stream(src: TokenStream) -> Vec<String> {
+    let mut variants = Vec::new();
+    let mut state = 0;
+    for it in src.into_iter() {
+        match state {
+            0 => {
+                // wait for variant name, skipping attributes
+                if let Ident(ident) = &it {
+                    variants.push(ident.to_string());
+                    state = 1;
+                }
+            }
+            1 => {
+                // wait for ',' or the discriminant
+                match &it {
+                    Punct(punct) if punct.as_char() == ',' => state = 0,
+                    Punct(punct) if punct.as_char() == '=' => state = 2,
+                    Group(_) => panic!("Only fieldless enums can be derived as YoshinoEnum."),
+                    _ => {}
+                }
+            }
+            2 => {
+                // skip the discriminant until ','
+                if let Punct(punct) = &it {
+                    if punct.as_char() == ',' {
+                        state = 0;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    variants
+}
+
+fn get_text_enum_field_code(enum_name: &str, variants: &[String]) -> String {
+    let from_arms: Vec<String> = variants
+        .iter()
+        .map(|variant| format!("\"{variant}\" => Ok({enum_name}::{variant}),"))
+        .collect();
+    let to_arms: Vec<String> = variants
+        .iter()
+        .map(|variant| format!("{enum_name}::{variant} => \"{variant}\","))
+        .collect();
+    format!(
+        "impl yoshino_core::Field for {enum_name} {{
+    type Storage = String;
+    fn from_db_data(
+        data: &yoshino_core::db::DbValue,
+    ) -> Result<{enum_name}, yoshino_core::db::DbError> {{
+        let expected = \"a variant name of {enum_name}\";
+        match data {{
+            yoshino_core::db::DbValue::Text(s) => match s.as_str() {{
+                {}
+                _ => Err(yoshino_core::types::get_type_mismatch_error(expected, data)),
+            }},
+            _ => Err(yoshino_core::types::get_type_mismatch_error(expected, data)),
+        }}
+    }}
+    fn to_db_data(&self) -> String {{
+        match self {{
+            {}
+        }}
+        .to_string()
+    }}
+    fn db_field_type() -> yoshino_core::db::DbDataType {{
+        yoshino_core::db::DbDataType::Text
+    }}
+}}",
+        from_arms.join("\n"),
+        to_arms.join("\n")
+    )
+}
+
+fn get_integer_enum_field_code(enum_name: &str, variants: &[String]) -> String {
+    let from_checks: Vec<String> = variants
+        .iter()
+        .map(|variant| {
+            format!(
+                "if *v == {enum_name}::{variant} as i64 {{
+                    return Ok({enum_name}::{variant});
+                }}"
+            )
+        })
+        .collect();
+    let to_arms: Vec<String> = variants
+        .iter()
+        .map(|variant| format!("{enum_name}::{variant} => {enum_name}::{variant} as i64,"))
+        .collect();
+    format!(
+        "impl yoshino_core::Field for {enum_name} {{
+    type Storage = i64;
+    fn from_db_data(
+        data: &yoshino_core::db::DbValue,
+    ) -> Result<{enum_name}, yoshino_core::db::DbError> {{
+        let expected = \"a discriminant of {enum_name}\";
+        match data {{
+            yoshino_core::db::DbValue::Int(v) => {{
+                {}
+                Err(yoshino_core::types::get_type_mismatch_error(expected, data))
+            }}
+            _ => Err(yoshino_core::types::get_type_mismatch_error(expected, data)),
+        }}
+    }}
+    fn to_db_data(&self) -> i64 {{
+        match self {{
+            {}
+        }}
+    }}
+    fn db_field_type() -> yoshino_core::db::DbDataType {{
+        yoshino_core::db::DbDataType::Int
+    }}
+}}",
+        from_checks.join("\n"),
+        to_arms.join("\n")
+    )
+}
+
+/// Get the code of the struct of typed column handles, like `CounterColumns` for `Counter`,
+/// and the `columns()` method to get it.
+fn get_columns_code(visibility: &str, struct_name: &str, fields: &[SchemaField]) -> String {
+    let column_fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            format!(
+                "    pub {}: yoshino_core::Column<{}>,",
+                field.name, field.field_type
+            )
+        })
+        .collect();
+    let column_values: Vec<String> = fields
+        .iter()
+        .map(|field| format!("{0}: yoshino_core::Column::new(\"{0}\"),", field.name))
+        .collect();
+    format!(
+        "/// Typed column handles of `{struct_name}`, which create conditions on its fields.
+#[derive(Clone, Copy, Debug)]
+{visibility} struct {struct_name}Columns {{
+{}
+}}
+impl {struct_name} {{
+    /// The typed column handles of the fields.
+    {visibility} fn columns() -> {struct_name}Columns {{
+        {struct_name}Columns {{
+            {}
+        }}
+    }}
+}}",
+        column_fields.join("\n"),
+        column_values.join("\n")
+    )
+}
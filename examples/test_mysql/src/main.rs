@@ -19,9 +19,10 @@ fn main() {
         stock: Some(7),
         bb: None
     };
-    adaptor.insert_record(record).unwrap();
+    let row_id = adaptor.insert_record(record).unwrap();
+    println!("inserted: {:?}", row_id);
 
     for item in adaptor.query_all::<Counter>().unwrap() {
-        println!("{:?}", item)
+        println!("{:?}", item.unwrap())
     }
 }
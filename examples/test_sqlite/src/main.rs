@@ -20,33 +20,45 @@ fn main() {
         yoshino_user::UserCredentialHashType::Sha256WithSalt(Bytes::from("salt")),
     );
     adaptor.insert_record(new_user).unwrap();
-    let query_result = adaptor.query_all::<User>().unwrap();
-    for user in query_result {
+    // collect the records before updating them, as the iterator borrows the adaptor
+    let users: Vec<User> = adaptor
+        .query_all::<User>()
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    for user in users {
         println!("user: {:?}", user);
         let mut new_user = user.clone();
         new_user.login_credential = UserCredential::new(
             Bytes::from("new_password"),
             yoshino_user::UserCredentialHashType::Sha256WithSalt(Bytes::from("salt2")),
         );
-        adaptor
-            .update_with_cond(Cond::is_row_id_equal_to(&user).unwrap(), new_user)
-            .unwrap();
+        adaptor.save(&mut new_user).unwrap();
     }
     println!(">> New users");
     for user in adaptor.query_all::<User>().unwrap() {
-        println!("user: {:?}", user);
+        println!("user: {:?}", user.unwrap());
     }
 
     adaptor.create_table_for_schema::<Counter>().unwrap();
     let p1 = Counter {name:"milk".to_string(), stock: Some(20), score: 1.02, bb: Some(vec![1u8, 2u8, 4u8, 9u8])};
     let p2 = Counter{name:"cream".to_string(), stock: None, score: 2.01, bb: None};
     let p3 = Counter{name:"apple".to_string(), stock: Some(30), score: 3.11, bb: Some(vec![0u8, 9u8, 99u8, 1u8])};
-    adaptor.insert_record(p1).unwrap();
-    adaptor.insert_record(p2).unwrap();
-    adaptor.insert_record(p3).unwrap();
-    let cond = Cond::is_null("stock") | Cond::integer_equal_to("stock", 20);
+    adaptor
+        .transaction(|tx| {
+            tx.insert_record(p1)?;
+            tx.insert_record(p2)?;
+            tx.insert_record(p3)
+        })
+        .unwrap();
+    let columns = Counter::columns();
+    let cond = columns.stock.is_null() | columns.stock.eq(20);
     let query_result = adaptor.query_with_cond::<Counter>(cond).unwrap();
     for p in query_result {
-        println!("{:?}", p);
+        println!("{:?}", p.unwrap());
+    }
+    let cond = columns.score.gt(2.0);
+    for p in adaptor.query_with_cond::<Counter>(cond).unwrap() {
+        println!("score > 2.0: {:?}", p.unwrap());
     }
 }
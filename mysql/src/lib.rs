@@ -2,73 +2,122 @@ use std::ffi::{CStr, CString};
 use std::marker::PhantomData;
 use std::os::raw::c_ulong;
 use std::ptr;
-use yoshino_core::db::{DbAdaptor, DbData, DbDataType, DbError};
-use yoshino_core::{Cond, Schema};
+use yoshino_core::db::{Aggregate, DbAdaptor, DbDataType, DbError, DbErrorKind, DbValue};
+use yoshino_core::{Cond, Order, Query, RowID, Schema};
 
 macro_rules! db_stmt_try {
     ($stmt: ident, $e: expr) => {{
         {
             let return_value = $e;
             if return_value != 0 {
-                let error_c = mysqlclient_sys::mysql_stmt_error($stmt);
-                let error_msg =
-                    String::from_utf8_lossy(CStr::from_ptr(error_c).to_bytes()).into_owned();
-                return Err(DbError(format!("MySQL database error: {}", error_msg)));
+                return Err(MySQLAdaptor::get_stmt_error($stmt));
+            }
+        }
+    }};
+    ($stmt: ident, $e: expr, $sql: expr) => {{
+        {
+            let return_value = $e;
+            if return_value != 0 {
+                return Err(MySQLAdaptor::get_stmt_error($stmt).with_sql($sql));
+            }
+        }
+    }};
+}
+
+macro_rules! db_handler_try {
+    ($handler: expr, $e: expr, $sql: expr) => {{
+        {
+            let return_value = $e;
+            if return_value != 0 {
+                return Err(MySQLAdaptor::get_handler_error($handler).with_sql($sql));
             }
         }
     }};
 }
+
+/// Maximum number of placeholders allowed in one MySQL prepared statement.
+const MAX_PLACEHOLDER_COUNT: usize = 65535;
+/// Maximum number of records inserted by one statement in a batch insertion.
+const MAX_INSERT_BATCH_SIZE: usize = 1000;
+
+/// Maximum number of values bound as placeholders in one IN list. The values of longer lists
+/// are written as literals.
+const MAX_IN_LIST_LENGTH: usize = 1000;
+
+/// Maximum length of text and binary fields in a unique key, in characters and bytes
+/// respectively.
+const UNIQUE_KEY_FIELD_MAX_LENGTH: usize = 255;
+
+/// Maximum total length of the columns of an index in InnoDB, in bytes.
+const MAX_INDEX_KEY_BYTES: usize = 3072;
+
+/// Client flag to make the affected rows of an `UPDATE` be the number of matched rows
+/// instead of changed rows.
+const CLIENT_FOUND_ROWS: c_ulong = 2;
+
 pub struct MySQLAdaptor {
     handler: *mut mysqlclient_sys::MYSQL,
+    transaction_depth: usize,
 }
 
 /// The container to hold a MySQL bind list with the reference to original data.
 struct MySQLBindList<'a> {
     // keep this as a phantom type for track data lifetime
-    _data: &'a Vec<Box<dyn DbData>>,
+    _data: &'a [DbValue],
     // length list will be deallocated after the bind is finised
-    length_list: Vec<u64>,
+    _length_list: Vec<u64>,
     binds: Vec<mysqlclient_sys::MYSQL_BIND>,
-    is_null_placeholder: i8,
 }
 
 impl<'a> MySQLBindList<'a> {
-    fn from_boxed_db_data_list(data: &'a Vec<Box<dyn DbData>>) -> MySQLBindList<'a> {
-        let length_list: Vec<u64> = data.iter().map(|x| x.db_data_len() as u64).collect();
+    fn from_values(data: &'a [DbValue]) -> MySQLBindList<'a> {
+        let mut length_list: Vec<u64> = data
+            .iter()
+            .map(|value| match value {
+                DbValue::Text(v) => v.len() as u64,
+                DbValue::Blob(v) => v.len() as u64,
+                _ => 0,
+            })
+            .collect();
+        let length_list_ptr = length_list.as_mut_ptr();
         let mut binds = vec![];
-        let mut return_value = MySQLBindList {
-            _data: data,
-            length_list: length_list,
-            binds: vec![],
-            is_null_placeholder: 1,
-        };
-        let length_list_ptr = return_value.length_list.as_mut_ptr();
-        for i in 0..data.len() {
+        for (i, value) in data.iter().enumerate() {
             unsafe {
                 let mut bind: mysqlclient_sys::MYSQL_BIND = std::mem::zeroed();
-                let data_item = &data[i];
-                bind.buffer = data_item.db_data_ptr() as *mut std::ffi::c_void;
-                bind.length = length_list_ptr.offset(i as isize);
-                bind.buffer_type = match data_item.db_data_type() {
-                    yoshino_core::db::DbDataType::Int | yoshino_core::db::DbDataType::NullableInt | yoshino_core::db::DbDataType::RowID => 
+                bind.length = length_list_ptr.add(i);
+                let (buffer, buffer_type) = match value {
+                    // a null value is bound with the null type, so no is_null flag is needed
+                    DbValue::Null => (
+                        ptr::null(),
+                        mysqlclient_sys::enum_field_types::MYSQL_TYPE_NULL,
+                    ),
+                    DbValue::Int(v) => (
+                        v as *const i64 as *const std::ffi::c_void,
                         mysqlclient_sys::enum_field_types::MYSQL_TYPE_LONGLONG,
-                    yoshino_core::db::DbDataType::Text | yoshino_core::db::DbDataType::NullableText => 
-                        mysqlclient_sys::enum_field_types::MYSQL_TYPE_STRING,
-                    yoshino_core::db::DbDataType::Float =>
+                    ),
+                    DbValue::Float(v) => (
+                        v as *const f64 as *const std::ffi::c_void,
                         mysqlclient_sys::enum_field_types::MYSQL_TYPE_DOUBLE,
-                    yoshino_core::db::DbDataType::Binary | yoshino_core::db::DbDataType::NullableBinary =>
-                        mysqlclient_sys::enum_field_types::MYSQL_TYPE_BLOB
-                };
-                bind.is_null = if data_item.db_data_ptr().is_null() {
-                    &mut return_value.is_null_placeholder
-                } else {
-                    0 as *mut i8
+                    ),
+                    DbValue::Text(v) => (
+                        v.as_ptr() as *const std::ffi::c_void,
+                        mysqlclient_sys::enum_field_types::MYSQL_TYPE_STRING,
+                    ),
+                    DbValue::Blob(v) => (
+                        v.as_ptr() as *const std::ffi::c_void,
+                        mysqlclient_sys::enum_field_types::MYSQL_TYPE_BLOB,
+                    ),
                 };
+                bind.buffer = buffer as *mut std::ffi::c_void;
+                bind.buffer_type = buffer_type;
                 binds.push(bind);
             }
         }
-        return_value.binds = binds;
-        return_value
+        MySQLBindList {
+            _data: data,
+            _length_list: length_list,
+            binds,
+        }
     }
 }
 
@@ -80,6 +129,7 @@ impl MySQLAdaptor {
         let c_db = CString::new(db).unwrap();
         let adaptor = MySQLAdaptor {
             handler: unsafe { mysqlclient_sys::mysql_init(ptr::null_mut()) },
+            transaction_depth: 0,
         };
         let connect_result = unsafe {
             mysqlclient_sys::mysql_real_connect(
@@ -90,24 +140,197 @@ impl MySQLAdaptor {
                 c_db.as_ptr(),
                 0,
                 ptr::null(),
-                0,
+                CLIENT_FOUND_ROWS,
             )
         };
-        return if connect_result.is_null() {
-            let error_message: String = unsafe {
-                let error_c = mysqlclient_sys::mysql_error(adaptor.handler);
-                String::from_utf8_lossy(CStr::from_ptr(error_c).to_bytes()).into_owned()
-            };
-            Err(DbError(format!(
-                "MySQL connection failure: {}",
-                error_message
-            )))
+        if connect_result.is_null() {
+            Err(unsafe { MySQLAdaptor::get_handler_error(adaptor.handler) })
         } else {
             Ok(adaptor)
+        }
+    }
+
+    /// Execute a SQL statement that doesn't return any rows without preparing it.
+    fn execute_query(&mut self, sql: &str) -> Result<(), DbError> {
+        let sql_cstring = CString::new(sql).unwrap();
+        unsafe {
+            db_handler_try!(
+                self.handler,
+                mysqlclient_sys::mysql_real_query(
+                    self.handler,
+                    sql_cstring.as_ptr(),
+                    sql.len() as c_ulong
+                ),
+                sql
+            );
+        }
+        Ok(())
+    }
+
+    /// Create a `DbError` from a MySQL error number and message.
+    fn get_error(errno: u32, message: String) -> DbError {
+        let kind = match errno {
+            // ER_DUP_ENTRY, ER_DUP_ENTRY_WITH_KEY_NAME
+            1062 | 1586 => DbErrorKind::UniqueViolation,
+            // ER_BAD_NULL_ERROR, ER_NO_DEFAULT_FOR_FIELD, ER_ROW_IS_REFERENCED_2,
+            // ER_NO_REFERENCED_ROW_2, ER_CHECK_CONSTRAINT_VIOLATED
+            1048 | 1364 | 1451 | 1452 | 3819 => DbErrorKind::ConstraintViolation,
+            // ER_LOCK_WAIT_TIMEOUT, ER_LOCK_DEADLOCK
+            1205 | 1213 => DbErrorKind::Busy,
+            // ER_TRUNCATED_WRONG_VALUE, ER_TRUNCATED_WRONG_VALUE_FOR_FIELD, WARN_DATA_OUT_OF_RANGE,
+            // ER_DATA_TOO_LONG
+            1292 | 1366 | 1264 | 1406 => DbErrorKind::TypeMismatch,
+            // ER_PARSE_ERROR
+            1064 => DbErrorKind::Syntax,
+            // CR_CONNECTION_ERROR, CR_CONN_HOST_ERROR, CR_UNKNOWN_HOST, CR_SERVER_GONE_ERROR,
+            // CR_SERVER_LOST, CR_SERVER_LOST_EXTENDED
+            2002 | 2003 | 2005 | 2006 | 2013 | 2055 => DbErrorKind::ConnectionLost,
+            _ => DbErrorKind::Other,
         };
+        DbError::new(kind, message).with_code(errno as i32)
+    }
+
+    fn get_out_of_memory_error() -> DbError {
+        DbError::new(
+            DbErrorKind::Other,
+            "MySQL database error: out of memory.".to_string(),
+        )
+    }
+
+    fn get_truncation_error(name: &str) -> DbError {
+        DbError::new(
+            DbErrorKind::TypeMismatch,
+            format!("MySQL database error: data of {} is truncated", name),
+        )
+        .with_code(mysqlclient_sys::MYSQL_DATA_TRUNCATED as i32)
+    }
+
+    /// Get the error of the last failed call on a statement.
+    unsafe fn get_stmt_error(stmt: *mut mysqlclient_sys::MYSQL_STMT) -> DbError {
+        let error_c = mysqlclient_sys::mysql_stmt_error(stmt);
+        let message = String::from_utf8_lossy(CStr::from_ptr(error_c).to_bytes()).into_owned();
+        MySQLAdaptor::get_error(mysqlclient_sys::mysql_stmt_errno(stmt), message)
+    }
+
+    /// Get the error of the last failed call on a connection.
+    unsafe fn get_handler_error(handler: *mut mysqlclient_sys::MYSQL) -> DbError {
+        let error_c = mysqlclient_sys::mysql_error(handler);
+        let message = String::from_utf8_lossy(CStr::from_ptr(error_c).to_bytes()).into_owned();
+        MySQLAdaptor::get_error(mysqlclient_sys::mysql_errno(handler), message)
+    }
+
+    fn get_savepoint_name(depth: usize) -> String {
+        format!("yoshino_savepoint_{}", depth)
+    }
+
+    /// Initialize and prepare a statement. The statement is closed if the preparation fails.
+    fn prepare_stmt(&mut self, sql: &str) -> Result<*mut mysqlclient_sys::MYSQL_STMT, DbError> {
+        let sql_cstring = CString::new(sql).unwrap();
+        unsafe {
+            let stmt = mysqlclient_sys::mysql_stmt_init(self.handler);
+            if stmt.is_null() {
+                return Err(MySQLAdaptor::get_out_of_memory_error());
+            }
+            let result = (|| {
+                db_stmt_try!(
+                    stmt,
+                    mysqlclient_sys::mysql_stmt_prepare(
+                        stmt,
+                        sql_cstring.as_ptr(),
+                        sql.len() as c_ulong
+                    ),
+                    sql
+                );
+                Ok(stmt)
+            })();
+            if result.is_err() {
+                mysqlclient_sys::mysql_stmt_close(stmt);
+            }
+            result
+        }
+    }
+
+    /// Bind the values to a prepared statement and execute it.
+    fn execute_stmt_with_values(
+        stmt: *mut mysqlclient_sys::MYSQL_STMT,
+        values: &[DbValue],
+    ) -> Result<(), DbError> {
+        let mut bind_list = MySQLBindList::from_values(values);
+        let bind_array = bind_list.binds.as_mut_ptr();
+        unsafe {
+            if !values.is_empty() {
+                db_stmt_try!(
+                    stmt,
+                    mysqlclient_sys::mysql_stmt_bind_param(stmt, bind_array)
+                );
+            }
+            db_stmt_try!(stmt, mysqlclient_sys::mysql_stmt_execute(stmt));
+        }
+        Ok(())
     }
 
-    fn get_create_table_stmt_code(schema_name: &str, fields: &Vec<(String, DbDataType)>) -> String {
+    /// Prepare and execute a statement that doesn't return any rows, and read the result of
+    /// the execution, like the affected rows, with `read_result`. The statement is closed
+    /// whether it succeeds or not.
+    fn execute_stmt<R>(
+        &mut self,
+        sql: &str,
+        values: &[DbValue],
+        read_result: impl FnOnce(*mut mysqlclient_sys::MYSQL_STMT) -> R,
+    ) -> Result<R, DbError> {
+        let stmt = self.prepare_stmt(sql)?;
+        let result = MySQLAdaptor::execute_stmt_with_values(stmt, values)
+            .map(|_| read_result(stmt))
+            .map_err(|error| error.with_sql(sql));
+        unsafe {
+            mysqlclient_sys::mysql_stmt_close(stmt);
+        }
+        result
+    }
+
+    /// Run a query that returns a single value, which is fetched into a buffer of
+    /// `buffer_type`. Returns `None` if there is no result or the value is null.
+    fn query_single_value<V: Default>(
+        &mut self,
+        sql: &str,
+        values: &[DbValue],
+        buffer_type: mysqlclient_sys::enum_field_types,
+    ) -> Result<Option<V>, DbError> {
+        let stmt = self.prepare_stmt(sql)?;
+        let result = (|| unsafe {
+            MySQLAdaptor::execute_stmt_with_values(stmt, values)
+                .map_err(|error| error.with_sql(sql))?;
+            let mut buffer = V::default();
+            let mut is_null: mysqlclient_sys::my_bool = 0;
+            let mut bind: mysqlclient_sys::MYSQL_BIND = std::mem::zeroed();
+            bind.buffer = (&mut buffer) as *mut V as *mut std::ffi::c_void;
+            bind.buffer_type = buffer_type;
+            bind.is_null = &mut is_null;
+            db_stmt_try!(
+                stmt,
+                mysqlclient_sys::mysql_stmt_bind_result(stmt, &mut bind)
+            );
+            let status = mysqlclient_sys::mysql_stmt_fetch(stmt);
+            if status == (mysqlclient_sys::MYSQL_NO_DATA as i32) {
+                return Ok(None);
+            }
+            if status == (mysqlclient_sys::MYSQL_DATA_TRUNCATED as i32) {
+                return Err(MySQLAdaptor::get_truncation_error(sql));
+            }
+            db_stmt_try!(stmt, status, sql);
+            Ok(if is_null != 0 { None } else { Some(buffer) })
+        })();
+        unsafe {
+            mysqlclient_sys::mysql_stmt_close(stmt);
+        }
+        result
+    }
+
+    fn get_create_table_stmt_code(
+        schema_name: &str,
+        fields: &Vec<(String, DbDataType)>,
+        unique_key: &[String],
+    ) -> String {
         let mut s = format!("CREATE TABLE IF NOT EXISTS {} (", schema_name);
         for i in 0..fields.len() {
             if i != 0 {
@@ -115,21 +338,213 @@ impl MySQLAdaptor {
             }
             let (field_name, field_type) = fields.get(i).unwrap();
             s = s + field_name + " ";
+            if unique_key.contains(field_name) {
+                if let Some(code) = MySQLAdaptor::get_unique_key_field_type_code(*field_type) {
+                    s = s + &code;
+                    continue;
+                }
+            }
             s = s + match field_type {
                 DbDataType::Int => "BIGINT NOT NULL",
                 DbDataType::NullableInt => "BIGINT",
                 DbDataType::Text => "TEXT NOT NULL",
                 DbDataType::NullableText => "TEXT",
-                DbDataType::Float => "DOUBLE",
+                DbDataType::Float => "DOUBLE NOT NULL",
+                DbDataType::NullableFloat => "DOUBLE",
                 DbDataType::RowID => "BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY",
                 DbDataType::Binary => "BLOB NOT NULL",
                 DbDataType::NullableBinary => "BLOB",
+                DbDataType::Bool => "TINYINT(1) NOT NULL",
+                DbDataType::NullableBool => "TINYINT(1)",
+                DbDataType::Int8 => "TINYINT NOT NULL",
+                DbDataType::NullableInt8 => "TINYINT",
+                DbDataType::Int16 => "SMALLINT NOT NULL",
+                DbDataType::NullableInt16 => "SMALLINT",
+                DbDataType::Int32 => "INT NOT NULL",
+                DbDataType::NullableInt32 => "INT",
+                DbDataType::UInt8 => "TINYINT UNSIGNED NOT NULL",
+                DbDataType::NullableUInt8 => "TINYINT UNSIGNED",
+                DbDataType::UInt16 => "SMALLINT UNSIGNED NOT NULL",
+                DbDataType::NullableUInt16 => "SMALLINT UNSIGNED",
+                DbDataType::UInt32 => "INT UNSIGNED NOT NULL",
+                DbDataType::NullableUInt32 => "INT UNSIGNED",
+                DbDataType::Float32 => "FLOAT NOT NULL",
+                DbDataType::NullableFloat32 => "FLOAT",
+                DbDataType::DateTime => "DATETIME(6) NOT NULL",
+                DbDataType::NullableDateTime => "DATETIME(6)",
+                DbDataType::Date => "DATE NOT NULL",
+                DbDataType::NullableDate => "DATE",
+                DbDataType::Uuid => "BINARY(16) NOT NULL",
+                DbDataType::NullableUuid => "BINARY(16)",
+                DbDataType::UuidKey => "BINARY(16) NOT NULL PRIMARY KEY",
+                DbDataType::Json => "JSON NOT NULL",
+                DbDataType::NullableJson => "JSON",
             }
         }
+        if !unique_key.is_empty() {
+            s = format!("{}, UNIQUE KEY ({})", s, unique_key.join(", "));
+        }
         s = s + ");";
         s
     }
 
+    /// The column type of a text or binary field in a unique key. MySQL can't index the full
+    /// value of TEXT and BLOB columns, so these fields are created as VARCHAR and VARBINARY
+    /// columns, and a longer value is rejected instead of being compared by its prefix.
+    fn get_unique_key_field_type_code(field_type: DbDataType) -> Option<String> {
+        let length = UNIQUE_KEY_FIELD_MAX_LENGTH;
+        match field_type {
+            DbDataType::Text => Some(format!("VARCHAR({}) NOT NULL", length)),
+            DbDataType::NullableText => Some(format!("VARCHAR({})", length)),
+            DbDataType::Binary => Some(format!("VARBINARY({}) NOT NULL", length)),
+            DbDataType::NullableBinary => Some(format!("VARBINARY({})", length)),
+            _ => None,
+        }
+    }
+
+    /// The maximum number of bytes a field takes in an index. A VARCHAR character takes up
+    /// to 4 bytes in utf8mb4. Return `None` for JSON fields, which can't be indexed.
+    fn get_unique_key_field_byte_length(field_type: DbDataType) -> Option<usize> {
+        match field_type {
+            DbDataType::Text | DbDataType::NullableText => Some(UNIQUE_KEY_FIELD_MAX_LENGTH * 4),
+            DbDataType::Binary | DbDataType::NullableBinary => Some(UNIQUE_KEY_FIELD_MAX_LENGTH),
+            DbDataType::Bool
+            | DbDataType::NullableBool
+            | DbDataType::Int8
+            | DbDataType::NullableInt8
+            | DbDataType::UInt8
+            | DbDataType::NullableUInt8 => Some(1),
+            DbDataType::Int16
+            | DbDataType::NullableInt16
+            | DbDataType::UInt16
+            | DbDataType::NullableUInt16 => Some(2),
+            DbDataType::Date | DbDataType::NullableDate => Some(3),
+            DbDataType::Int32
+            | DbDataType::NullableInt32
+            | DbDataType::UInt32
+            | DbDataType::NullableUInt32
+            | DbDataType::Float32
+            | DbDataType::NullableFloat32 => Some(4),
+            DbDataType::Int
+            | DbDataType::NullableInt
+            | DbDataType::Float
+            | DbDataType::NullableFloat
+            | DbDataType::RowID
+            | DbDataType::DateTime
+            | DbDataType::NullableDateTime => Some(8),
+            DbDataType::Uuid | DbDataType::NullableUuid | DbDataType::UuidKey => Some(16),
+            DbDataType::Json | DbDataType::NullableJson => None,
+        }
+    }
+
+    /// Check that the unique key can be created by InnoDB, whose index keys are limited to
+    /// 3072 bytes. A unique key can hold at most three text fields.
+    fn check_unique_key_length(
+        schema_name: &str,
+        fields: &[(String, DbDataType)],
+        unique_key: &[String],
+    ) -> Result<(), DbError> {
+        let mut key_bytes = 0;
+        for (field_name, field_type) in fields {
+            if !unique_key.contains(field_name) {
+                continue;
+            }
+            match MySQLAdaptor::get_unique_key_field_byte_length(*field_type) {
+                Some(bytes) => key_bytes += bytes,
+                None => {
+                    return Err(DbError::new(
+                        DbErrorKind::Other,
+                        format!(
+                            "MySQL database error: JSON field {}.{} can't be in the unique key",
+                            schema_name, field_name
+                        ),
+                    ))
+                }
+            }
+        }
+        if key_bytes > MAX_INDEX_KEY_BYTES {
+            return Err(DbError::new(
+                DbErrorKind::Other,
+                format!(
+                    "MySQL database error: the unique key of {} takes up to {} bytes, more than \
+                     the limit of {} bytes",
+                    schema_name, key_bytes, MAX_INDEX_KEY_BYTES
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that the text and binary values of the unique key fit in their VARCHAR and
+    /// VARBINARY columns. MySQL would truncate a longer value unless the session is in strict
+    /// mode, so it's rejected before being sent.
+    fn check_unique_key_values(
+        schema_name: &str,
+        fields: &[(String, DbDataType)],
+        unique_key: &[String],
+        values: &[DbValue],
+    ) -> Result<(), DbError> {
+        for ((field_name, _), value) in fields.iter().zip(values) {
+            if !unique_key.contains(field_name) {
+                continue;
+            }
+            let length = match value {
+                DbValue::Text(text) => text.chars().count(),
+                DbValue::Blob(blob) => blob.len(),
+                _ => continue,
+            };
+            if length > UNIQUE_KEY_FIELD_MAX_LENGTH {
+                return Err(DbError::new(
+                    DbErrorKind::TypeMismatch,
+                    format!(
+                        "MySQL database error: the value of {}.{} in the unique key is longer \
+                         than {}",
+                        schema_name, field_name, UNIQUE_KEY_FIELD_MAX_LENGTH
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The statement to insert a record or update the existing record with the same
+    /// unique key. The insert id is set to the row id of the updated record if there is a
+    /// RowID field. The inserted values are referred to by the row alias `new`, which
+    /// requires MySQL 8.0.19 or later.
+    fn get_upsert_stmt_code(
+        schema_name: &str,
+        fields: &Vec<(String, DbDataType)>,
+        key: &[String],
+    ) -> String {
+        let insert_stmt = MySQLAdaptor::get_insert_value_stmt_code(schema_name, fields);
+        let mut update_fields: Vec<&String> = fields
+            .iter()
+            .filter(|(name, field_type)| *field_type != DbDataType::RowID && !key.contains(name))
+            .map(|(name, _)| name)
+            .collect();
+        if update_fields.is_empty() {
+            update_fields = key.iter().collect();
+        }
+        let mut assignments: Vec<String> = update_fields
+            .iter()
+            .map(|name| format!("{} = new.{}", name, name))
+            .collect();
+        if let Some((row_id_field, _)) = fields
+            .iter()
+            .find(|(_, field_type)| *field_type == DbDataType::RowID)
+        {
+            assignments.push(format!(
+                "{} = LAST_INSERT_ID({})",
+                row_id_field, row_id_field
+            ));
+        }
+        format!(
+            "{} AS new ON DUPLICATE KEY UPDATE {};",
+            insert_stmt.trim_end_matches(';'),
+            assignments.join(", ")
+        )
+    }
+
     fn get_insert_value_stmt_code(schema_name: &str, fields: &Vec<(String, DbDataType)>) -> String {
         let mut fields_part = String::new();
         let mut fields_value_tokens = String::new();
@@ -148,6 +563,48 @@ impl MySQLAdaptor {
         )
     }
 
+    /// The statement to insert `row_count` records with a multi-row `VALUES` list.
+    fn get_batch_insert_value_stmt_code(
+        schema_name: &str,
+        fields: &[(String, DbDataType)],
+        row_count: usize,
+    ) -> String {
+        let fields_part: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+        let row_value_tokens = vec!["?"; fields.len()].join(", ");
+        let rows_part = vec![format!("({})", row_value_tokens); row_count].join(", ");
+        format!(
+            "INSERT INTO {} ({}) VALUES {};",
+            schema_name,
+            fields_part.join(", "),
+            rows_part
+        )
+    }
+
+    /// The number of records inserted by one statement in a batch insertion. It's limited
+    /// by the maximum number of placeholders in one MySQL prepared statement.
+    fn get_insert_batch_size(field_count: usize) -> usize {
+        (MAX_PLACEHOLDER_COUNT / field_count.max(1)).clamp(1, MAX_INSERT_BATCH_SIZE)
+    }
+
+    /// Check that there is no active transaction before running a DDL statement like CREATE
+    /// TABLE, DROP TABLE or TRUNCATE TABLE, which would commit the transaction implicitly.
+    fn check_no_active_transaction(
+        transaction_depth: usize,
+        operation: &str,
+    ) -> Result<(), DbError> {
+        if transaction_depth > 0 {
+            return Err(DbError::new(
+                DbErrorKind::Other,
+                format!(
+                    "MySQL database error: can't {} inside a transaction, as it would commit \
+                     the transaction implicitly",
+                    operation
+                ),
+            ));
+        }
+        Ok(())
+    }
+
     fn get_query_clause_code(schema_name: &str, fields: &Vec<(String, DbDataType)>) -> String {
         let mut fields_str = String::new();
         for i in 0..fields.len() {
@@ -172,31 +629,270 @@ impl MySQLAdaptor {
         format!("UPDATE {} SET {}", schema_name, fields_str)
     }
 
-    fn get_cond_expression_code_and_data(cond: Cond) -> (String, Vec<Box<dyn DbData>>) {
+    fn get_aggregate_expression_code(aggregate: Aggregate, field_name: &str) -> String {
+        match aggregate {
+            Aggregate::Sum => format!("SUM({})", field_name),
+            Aggregate::Min => format!("MIN({})", field_name),
+            Aggregate::Max => format!("MAX({})", field_name),
+            Aggregate::Avg => format!("AVG({})", field_name),
+        }
+    }
+
+    /// Generate the aggregate query statement with an optional condition.
+    fn get_aggregate_stmt_code_and_data(
+        schema_name: &str,
+        aggregate_expression: &str,
+        cond: Option<Cond>,
+    ) -> (String, Vec<DbValue>) {
+        let aggregate_clause = format!("SELECT {} FROM {}", aggregate_expression, schema_name);
+        match cond {
+            Some(cond) => {
+                let (cond_clause, cond_values) =
+                    MySQLAdaptor::get_cond_expression_code_and_data(cond);
+                (format!("{} WHERE {};", aggregate_clause, cond_clause), cond_values)
+            }
+            None => (format!("{};", aggregate_clause), vec![]),
+        }
+    }
+
+    fn get_order_by_code(order_by: &[(String, Order)]) -> String {
+        let keys: Vec<String> = order_by
+            .iter()
+            .map(|(field_name, order)| match order {
+                Order::Asc => format!("{} ASC", field_name),
+                Order::Desc => format!("{} DESC", field_name),
+            })
+            .collect();
+        format!("ORDER BY {}", keys.join(", "))
+    }
+
+    fn get_limit_code(limit: Option<u64>, offset: Option<u64>) -> String {
+        match (limit, offset) {
+            (Some(limit), Some(offset)) => format!("LIMIT {} OFFSET {}", limit, offset),
+            (Some(limit), None) => format!("LIMIT {}", limit),
+            // MySQL doesn't support OFFSET without LIMIT, so use the largest limit instead
+            (None, Some(offset)) => format!("LIMIT {} OFFSET {}", u64::MAX, offset),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// The condition that the field is in, or not in if `negated`, the list of `values`.
+    ///
+    /// The values of a long list are written as literals instead of placeholders, so that the
+    /// statement stays under the limit of placeholders.
+    fn get_in_list_code_and_data(
+        field_name: &str,
+        values: Vec<DbValue>,
+        negated: bool,
+    ) -> (String, Vec<DbValue>) {
+        if values.is_empty() {
+            // MySQL doesn't accept empty lists, and no value is in an empty list
+            return (if negated { "TRUE" } else { "FALSE" }.to_string(), values);
+        }
+        let operator = if negated { "NOT IN" } else { "IN" };
+        if values.len() <= MAX_IN_LIST_LENGTH {
+            let placeholders = vec!["?"; values.len()].join(", ");
+            return (format!("{} {} ({})", field_name, operator, placeholders), values);
+        }
+        let literals: Vec<String> = values.iter().map(MySQLAdaptor::get_literal_code).collect();
+        (format!("{} {} ({})", field_name, operator, literals.join(", ")), vec![])
+    }
+
+    /// The SQL literal of a value. Texts and blobs are written as hex literals, so that no
+    /// escaping is needed whatever the SQL mode is.
+    fn get_literal_code(value: &DbValue) -> String {
+        let get_hex = |bytes: &[u8]| -> String {
+            bytes.iter().map(|b| format!("{:02X}", b)).collect()
+        };
+        match value {
+            DbValue::Null => "NULL".to_owned(),
+            DbValue::Int(v) => v.to_string(),
+            // MySQL can't store non-finite floats, so they are equal to nothing
+            DbValue::Float(v) if v.is_finite() => format!("{:?}", v),
+            DbValue::Float(_) => "NULL".to_owned(),
+            DbValue::Text(v) => format!("_utf8mb4 X'{}'", get_hex(v.as_bytes())),
+            DbValue::Blob(v) => format!("X'{}'", get_hex(v)),
+        }
+    }
+
+    /// Translate a glob pattern to an equivalent regular expression.
+    fn get_regexp_from_glob(pattern: &str) -> String {
+        let mut regexp = "^".to_string();
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' => regexp.push_str(".*"),
+                '?' => regexp.push('.'),
+                '[' => {
+                    // copy the character class, where a leading ']' is a literal
+                    regexp.push('[');
+                    if let Some('^') = chars.peek() {
+                        regexp.push(chars.next().unwrap());
+                    }
+                    if let Some(']') = chars.peek() {
+                        regexp.push_str(&format!("\\{}", chars.next().unwrap()));
+                    }
+                    for c in chars.by_ref() {
+                        match c {
+                            ']' => break,
+                            // `&&` is the intersection of sets in ICU regexps
+                            '\\' | '[' | '&' => regexp.push_str(&format!("\\{}", c)),
+                            _ => regexp.push(c),
+                        }
+                    }
+                    regexp.push(']');
+                }
+                '.' | '^' | '$' | '+' | '(' | ')' | '{' | '}' | '|' | '\\' | ']' => {
+                    regexp.push_str(&format!("\\{}", c));
+                }
+                _ => regexp.push(c),
+            }
+        }
+        regexp.push('$');
+        regexp
+    }
+
+    fn get_cond_expression_code_and_data(cond: Cond) -> (String, Vec<DbValue>) {
         match cond {
+            Cond::In { field_name, values } => {
+                MySQLAdaptor::get_in_list_code_and_data(&field_name, values, false)
+            }
+            Cond::NotIn { field_name, values } => {
+                MySQLAdaptor::get_in_list_code_and_data(&field_name, values, true)
+            }
+            Cond::EqualTo { field_name, value } => (format!("{} = ?", field_name), vec![value]),
+            Cond::NotEqualTo { field_name, value } => {
+                (format!("{} <> ?", field_name), vec![value])
+            }
+            Cond::GreaterThan { field_name, value } => {
+                (format!("{} > ?", field_name), vec![value])
+            }
+            Cond::LessThan { field_name, value } => (format!("{} < ?", field_name), vec![value]),
+            Cond::GreaterThanOrEqualTo { field_name, value } => {
+                (format!("{} >= ?", field_name), vec![value])
+            }
+            Cond::LessThanOrEqualTo { field_name, value } => {
+                (format!("{} <= ?", field_name), vec![value])
+            }
             Cond::IntegerEqualTo { field_name, value } => {
-                (format!("{} = ?", field_name), vec![Box::new(value)])
+                (format!("{} = ?", field_name), vec![DbValue::Int(value)])
             }
             Cond::IntegerNotEqualTo { field_name, value } => {
-                (format!("{} <> ?", field_name), vec![Box::new(value)])
+                (format!("{} <> ?", field_name), vec![DbValue::Int(value)])
             }
             Cond::IntegerGreaterThan { field_name, value } => {
-                (format!("{} > ?", field_name), vec![Box::new(value)])
+                (format!("{} > ?", field_name), vec![DbValue::Int(value)])
             }
             Cond::IntegerGreaterThanOrEqualTo { field_name, value } => {
-                (format!("{} >= ?", field_name), vec![Box::new(value)])
+                (format!("{} >= ?", field_name), vec![DbValue::Int(value)])
             }
             Cond::IntegerLessThan { field_name, value } => {
-                (format!("{} < ?", field_name), vec![Box::new(value)])
+                (format!("{} < ?", field_name), vec![DbValue::Int(value)])
             }
             Cond::IntegerLessThanOrEqualTo { field_name, value } => {
-                (format!("{} <= ?", field_name), vec![Box::new(value)])
+                (format!("{} <= ?", field_name), vec![DbValue::Int(value)])
             }
             Cond::IsNotNull { field_name } => (format!("{} IS NOT NULL", field_name), vec![]),
             Cond::IsNull { field_name } => (format!("{} IS NULL", field_name), vec![]),
+            Cond::FloatEqualTo {
+                field_name,
+                value,
+                epsilon: None,
+            } => (format!("{} = ?", field_name), vec![DbValue::Float(value)]),
+            Cond::FloatEqualTo {
+                field_name,
+                value,
+                epsilon: Some(epsilon),
+            } => (
+                format!("{} BETWEEN ? AND ?", field_name),
+                vec![DbValue::Float(value - epsilon), DbValue::Float(value + epsilon)],
+            ),
+            Cond::FloatGreaterThan { field_name, value } => {
+                (format!("{} > ?", field_name), vec![DbValue::Float(value)])
+            }
+            Cond::FloatLessThan { field_name, value } => {
+                (format!("{} < ?", field_name), vec![DbValue::Float(value)])
+            }
+            Cond::FloatGreaterThanOrEqualTo { field_name, value } => {
+                (format!("{} >= ?", field_name), vec![DbValue::Float(value)])
+            }
+            Cond::FloatLessThanOrEqualTo { field_name, value } => {
+                (format!("{} <= ?", field_name), vec![DbValue::Float(value)])
+            }
+            Cond::BinaryEqualTo { field_name, value } => {
+                (format!("{} = ?", field_name), vec![DbValue::Blob(value)])
+            }
+            Cond::NullSafeEqualTo { field_name, value } => {
+                (format!("{} <=> ?", field_name), vec![value])
+            }
             Cond::TextEqualTo { field_name, value } => {
-                (format!("{} = ?", field_name), vec![Box::new(value)])
+                (format!("{} = ?", field_name), vec![DbValue::Text(value)])
+            }
+            Cond::TextNotEqualTo { field_name, value } => {
+                (format!("{} <> ?", field_name), vec![DbValue::Text(value)])
             }
+            Cond::TextGreaterThan { field_name, value } => {
+                (format!("{} > ?", field_name), vec![DbValue::Text(value)])
+            }
+            Cond::TextLessThan { field_name, value } => {
+                (format!("{} < ?", field_name), vec![DbValue::Text(value)])
+            }
+            Cond::TextEqualToIgnoreCase { field_name, value } => (
+                format!("LOWER({}) = LOWER(?)", field_name),
+                vec![DbValue::Text(value)],
+            ),
+            Cond::Like {
+                field_name,
+                pattern,
+                escape,
+            } => {
+                let code = match escape {
+                    Some(escape) => format!("{} LIKE ? ESCAPE '{}'", field_name, escape),
+                    None => format!("{} LIKE ?", field_name),
+                };
+                (code, vec![DbValue::Text(pattern)])
+            }
+            // MySQL has no GLOB, so the pattern is translated to a case sensitive regexp
+            Cond::Glob {
+                field_name,
+                pattern,
+            } => (
+                format!("REGEXP_LIKE({}, ?, 'c')", field_name),
+                vec![DbValue::Text(MySQLAdaptor::get_regexp_from_glob(&pattern))],
+            ),
+            Cond::Before { field_name, value } => (format!("{} < ?", field_name), vec![value]),
+            Cond::After { field_name, value } => (format!("{} > ?", field_name), vec![value]),
+            Cond::Between {
+                field_name,
+                start,
+                end,
+            } => (format!("{} BETWEEN ? AND ?", field_name), vec![start, end]),
+            Cond::JsonPathEqualTo {
+                field_name,
+                path,
+                value,
+            } => match value {
+                // JSON booleans are compared as 1 and 0, as SQLite does
+                DbValue::Int(_) | DbValue::Float(_) => (
+                    format!(
+                        "(CASE JSON_TYPE(JSON_EXTRACT({f}, ?)) \
+                         WHEN 'BOOLEAN' THEN (JSON_EXTRACT({f}, ?) = CAST('true' AS JSON)) = ? \
+                         ELSE JSON_EXTRACT({f}, ?) = ? END)",
+                        f = field_name
+                    ),
+                    vec![
+                        DbValue::Text(path.clone()),
+                        DbValue::Text(path.clone()),
+                        value.clone(),
+                        DbValue::Text(path),
+                        value,
+                    ],
+                ),
+                _ => (
+                    format!("JSON_EXTRACT({}, ?) = ?", field_name),
+                    vec![DbValue::Text(path), value],
+                ),
+            },
             Cond::Not { cond } => {
                 let (code, values) = MySQLAdaptor::get_cond_expression_code_and_data(*cond);
                 (format!("NOT ({})", code), values)
@@ -230,216 +926,357 @@ impl Drop for MySQLAdaptor {
 }
 
 impl DbAdaptor for MySQLAdaptor {
-    type Iterator<T: Schema> = MySQLResultIterator<T>;
+    type Iterator<'a, T: Schema> = MySQLResultIterator<'a, T>;
     fn create_table_for_schema<T: yoshino_core::types::Schema>(
         &mut self,
     ) -> Result<(), yoshino_core::db::DbError> {
+        MySQLAdaptor::check_no_active_transaction(self.transaction_depth, "create a table")?;
+        let schema_name = T::get_schema_name();
+        let fields = T::get_fields();
+        let unique_key = T::get_unique_key();
+        MySQLAdaptor::check_unique_key_length(&schema_name, &fields, &unique_key)?;
         let create_table_stmt =
-            MySQLAdaptor::get_create_table_stmt_code(&T::get_schema_name(), &T::get_fields());
-        let stmt_cstring = CString::new(create_table_stmt.as_str()).unwrap();
-        unsafe {
-            let stmt = mysqlclient_sys::mysql_stmt_init(self.handler);
-            if stmt.is_null() {
-                return Err(DbError(format!("Mysql database error: out of memory.")));
-            }
-            db_stmt_try!(
-                stmt,
-                mysqlclient_sys::mysql_stmt_prepare(
-                    stmt,
-                    stmt_cstring.as_ptr(),
-                    create_table_stmt.len() as c_ulong
-                )
-            );
-            db_stmt_try!(stmt, mysqlclient_sys::mysql_stmt_execute(stmt));
-            mysqlclient_sys::mysql_stmt_close(stmt);
+            MySQLAdaptor::get_create_table_stmt_code(&schema_name, &fields, &unique_key);
+        self.execute_stmt(&create_table_stmt, &[], |_| ())
+    }
+
+    fn insert_values<T: yoshino_core::types::Schema>(
+        &mut self,
+        values: Vec<DbValue>,
+    ) -> Result<RowID, yoshino_core::db::DbError> {
+        let schema_name = T::get_schema_name();
+        let fields = T::get_fields();
+        let unique_key = T::get_unique_key();
+        MySQLAdaptor::check_unique_key_values(&schema_name, &fields, &unique_key, &values)?;
+        let insert_value_stmt = MySQLAdaptor::get_insert_value_stmt_code(&schema_name, &fields);
+        let insert_id = self.execute_stmt(&insert_value_stmt, &values, |stmt| unsafe {
+            mysqlclient_sys::mysql_stmt_insert_id(stmt)
+        })?;
+        if insert_id == 0 {
+            Ok(RowID::NEW)
+        } else {
+            Ok(RowID::ID(insert_id as i64))
         }
-        Ok(())
     }
 
-    fn insert_record<T: yoshino_core::types::Schema>(
+    fn insert_records<T: yoshino_core::types::Schema, I: IntoIterator<Item = T>>(
         &mut self,
-        record: T,
-    ) -> Result<(), yoshino_core::db::DbError> {
-        let insert_value_stmt =
-            MySQLAdaptor::get_insert_value_stmt_code(&T::get_schema_name(), &T::get_fields());
-        let stmt_cstring = CString::new(insert_value_stmt.as_str()).unwrap();
-        unsafe {
-            let stmt = mysqlclient_sys::mysql_stmt_init(self.handler);
-            if stmt.is_null() {
-                return Err(DbError(format!("Mysql database error: out of memory.")));
+        records: I,
+    ) -> Result<usize, yoshino_core::db::DbError> {
+        let schema_name = T::get_schema_name();
+        let fields = T::get_fields();
+        let unique_key = T::get_unique_key();
+        let batch_size = MySQLAdaptor::get_insert_batch_size(fields.len());
+        self.transaction(|tx| {
+            // the statement for full batches is prepared once and reused
+            let mut batch_stmt: *mut mysqlclient_sys::MYSQL_STMT = ptr::null_mut();
+            let result = (|| {
+                let mut count = 0;
+                let mut values: Vec<DbValue> = vec![];
+                let mut row_count = 0;
+                for mut record in records {
+                    record.generate_keys();
+                    let record_values = record.get_values();
+                    MySQLAdaptor::check_unique_key_values(
+                        &schema_name,
+                        &fields,
+                        &unique_key,
+                        &record_values,
+                    )?;
+                    values.extend(record_values);
+                    row_count += 1;
+                    if row_count == batch_size {
+                        if batch_stmt.is_null() {
+                            batch_stmt = tx.prepare_stmt(
+                                &MySQLAdaptor::get_batch_insert_value_stmt_code(
+                                    &schema_name,
+                                    &fields,
+                                    batch_size,
+                                ),
+                            )?;
+                        }
+                        MySQLAdaptor::execute_stmt_with_values(batch_stmt, &values)?;
+                        count += row_count;
+                        values.clear();
+                        row_count = 0;
+                    }
+                }
+                if row_count > 0 {
+                    let stmt = tx.prepare_stmt(&MySQLAdaptor::get_batch_insert_value_stmt_code(
+                        &schema_name,
+                        &fields,
+                        row_count,
+                    ))?;
+                    let last_batch_result = MySQLAdaptor::execute_stmt_with_values(stmt, &values);
+                    unsafe {
+                        mysqlclient_sys::mysql_stmt_close(stmt);
+                    }
+                    last_batch_result?;
+                    count += row_count;
+                }
+                Ok(count)
+            })();
+            if !batch_stmt.is_null() {
+                unsafe {
+                    mysqlclient_sys::mysql_stmt_close(batch_stmt);
+                }
             }
-            db_stmt_try!(
-                stmt,
-                mysqlclient_sys::mysql_stmt_prepare(
-                    stmt,
-                    stmt_cstring.as_ptr(),
-                    insert_value_stmt.len() as c_ulong
-                )
-            );
-            let values = record.get_values();
-            let mut bind_list = MySQLBindList::from_boxed_db_data_list(&values);
-            let bind_array = bind_list.binds.as_mut_ptr();
-            db_stmt_try!(
-                stmt,
-                mysqlclient_sys::mysql_stmt_bind_param(stmt, bind_array)
-            );
-            db_stmt_try!(stmt, mysqlclient_sys::mysql_stmt_execute(stmt));
-            mysqlclient_sys::mysql_stmt_close(stmt);
+            result
+        })
+    }
+
+    fn upsert_record<T: yoshino_core::types::Schema>(
+        &mut self,
+        mut record: T,
+    ) -> Result<RowID, yoshino_core::db::DbError> {
+        let schema_name = T::get_schema_name();
+        let key = match T::get_upsert_key() {
+            Some(key) => key,
+            None => {
+                return Err(DbError::new(
+                    DbErrorKind::Other,
+                    format!(
+                        "MySQL database error: {} has neither unique key nor primary key field",
+                        schema_name
+                    ),
+                ))
+            }
+        };
+        let fields = T::get_fields();
+        let upsert_stmt = MySQLAdaptor::get_upsert_stmt_code(&schema_name, &fields, &key);
+        record.generate_keys();
+        let values = record.get_values();
+        let unique_key = T::get_unique_key();
+        MySQLAdaptor::check_unique_key_values(&schema_name, &fields, &unique_key, &values)?;
+        let insert_id = self.execute_stmt(&upsert_stmt, &values, |stmt| unsafe {
+            mysqlclient_sys::mysql_stmt_insert_id(stmt)
+        })?;
+        if insert_id == 0 {
+            Ok(RowID::NEW)
+        } else {
+            Ok(RowID::ID(insert_id as i64))
         }
-        Ok(())
     }
 
-    fn query_all<T: yoshino_core::types::Schema>(
+    fn query<T: yoshino_core::types::Schema>(
         &mut self,
-    ) -> Result<MySQLResultIterator<T>, DbError> {
-        let query_stmt = format!(
-            "{};",
-            MySQLAdaptor::get_query_clause_code(&T::get_schema_name(), &T::get_fields())
-        );
-        let stmt_cstring = CString::new(query_stmt.as_str()).unwrap();
-        unsafe {
-            let stmt = mysqlclient_sys::mysql_stmt_init(self.handler);
-            if stmt.is_null() {
-                return Err(DbError(format!("Mysql database error: out of memory.")));
+        query: Query<T>,
+    ) -> Result<MySQLResultIterator<'_, T>, yoshino_core::db::DbError> {
+        let mut query_stmt =
+            MySQLAdaptor::get_query_clause_code(&T::get_schema_name(), &T::get_fields());
+        let mut cond_values = vec![];
+        if let Some(cond) = query.cond {
+            let (cond_clause, values) = MySQLAdaptor::get_cond_expression_code_and_data(cond);
+            query_stmt = format!("{} WHERE {}", query_stmt, cond_clause);
+            cond_values = values;
+        }
+        if !query.order_by.is_empty() {
+            let order_by_code = MySQLAdaptor::get_order_by_code(&query.order_by);
+            query_stmt = format!("{} {}", query_stmt, order_by_code);
+        }
+        if query.limit.is_some() || query.offset.is_some() {
+            let limit_code = MySQLAdaptor::get_limit_code(query.limit, query.offset);
+            query_stmt = format!("{} {}", query_stmt, limit_code);
+        }
+        query_stmt += ";";
+        let stmt = self.prepare_stmt(&query_stmt)?;
+        if let Err(error) = MySQLAdaptor::execute_stmt_with_values(stmt, &cond_values) {
+            unsafe {
+                mysqlclient_sys::mysql_stmt_close(stmt);
             }
-            db_stmt_try!(
-                stmt,
-                mysqlclient_sys::mysql_stmt_prepare(
-                    stmt,
-                    stmt_cstring.as_ptr(),
-                    query_stmt.len() as c_ulong
-                )
-            );
-            db_stmt_try!(stmt, mysqlclient_sys::mysql_stmt_execute(stmt));
-            MySQLResultIterator::new(stmt)
+            return Err(error.with_sql(&query_stmt));
         }
+        MySQLResultIterator::new(stmt)
     }
 
-    fn query_with_cond<T: yoshino_core::types::Schema>(
+    fn drop_table_for_schema<T: yoshino_core::types::Schema>(&mut self) -> Result<(), DbError> {
+        MySQLAdaptor::check_no_active_transaction(self.transaction_depth, "drop a table")?;
+        self.execute_query(&format!("DROP TABLE IF EXISTS {};", T::get_schema_name()))
+    }
+
+    fn truncate_table_for_schema<T: yoshino_core::types::Schema>(
         &mut self,
-        cond: yoshino_core::Cond,
-    ) -> Result<MySQLResultIterator<T>, yoshino_core::db::DbError> {
-        let (cond_clause, cond_values) = MySQLAdaptor::get_cond_expression_code_and_data(cond);
-        let query_stmt = format!(
-            "{} WHERE {};",
-            MySQLAdaptor::get_query_clause_code(&T::get_schema_name(), &T::get_fields()),
-            cond_clause
-        );
-        let stmt_cstring = CString::new(query_stmt.as_str()).unwrap();
-        unsafe {
-            let stmt = mysqlclient_sys::mysql_stmt_init(self.handler);
-            if stmt.is_null() {
-                return Err(DbError(format!("Mysql database error: out of memory.")));
+    ) -> Result<(), DbError> {
+        MySQLAdaptor::check_no_active_transaction(self.transaction_depth, "truncate a table")?;
+        self.execute_query(&format!("TRUNCATE TABLE {};", T::get_schema_name()))
+    }
+
+    fn table_exists_for_schema<T: yoshino_core::types::Schema>(
+        &mut self,
+    ) -> Result<bool, DbError> {
+        let values = vec![DbValue::Text(T::get_schema_name())];
+        let count: Option<i64> = self.query_single_value(
+            "SELECT COUNT(*) FROM information_schema.tables \
+             WHERE table_schema = DATABASE() AND table_name = ?;",
+            &values,
+            mysqlclient_sys::enum_field_types::MYSQL_TYPE_LONGLONG,
+        )?;
+        Ok(count.unwrap_or(0) > 0)
+    }
+
+    fn count<T: yoshino_core::types::Schema>(
+        &mut self,
+        cond: Option<Cond>,
+    ) -> Result<i64, yoshino_core::db::DbError> {
+        let (count_stmt, values) =
+            MySQLAdaptor::get_aggregate_stmt_code_and_data(&T::get_schema_name(), "COUNT(*)", cond);
+        let count = self.query_single_value(
+            &count_stmt,
+            &values,
+            mysqlclient_sys::enum_field_types::MYSQL_TYPE_LONGLONG,
+        )?;
+        Ok(count.unwrap_or(0))
+    }
+
+    fn aggregate<T: yoshino_core::types::Schema>(
+        &mut self,
+        aggregate: Aggregate,
+        field_name: &str,
+        cond: Option<Cond>,
+    ) -> Result<Option<DbValue>, yoshino_core::db::DbError> {
+        let schema_name = T::get_schema_name();
+        let field_type = match T::get_field_type(field_name) {
+            Some(field_type) if field_type.is_numeric() => field_type,
+            _ => {
+                return Err(DbError::new(
+                    DbErrorKind::TypeMismatch,
+                    format!(
+                        "MySQL database error: {} is not a numeric field of {}",
+                        field_name, schema_name
+                    ),
+                ))
             }
-            db_stmt_try!(
-                stmt,
-                mysqlclient_sys::mysql_stmt_prepare(
-                    stmt,
-                    stmt_cstring.as_ptr(),
-                    query_stmt.len() as c_ulong
-                )
-            );
-            let mut bind_list = MySQLBindList::from_boxed_db_data_list(&cond_values);
-            let bind_array = bind_list.binds.as_mut_ptr();
-            db_stmt_try!(
-                stmt,
-                mysqlclient_sys::mysql_stmt_bind_param(stmt, bind_array)
-            );
-            db_stmt_try!(stmt, mysqlclient_sys::mysql_stmt_execute(stmt));
-            MySQLResultIterator::new(stmt)
+        };
+        let aggregate_expression =
+            MySQLAdaptor::get_aggregate_expression_code(aggregate, field_name);
+        let (aggregate_stmt, values) = MySQLAdaptor::get_aggregate_stmt_code_and_data(
+            &schema_name,
+            &aggregate_expression,
+            cond,
+        );
+        if aggregate.has_integer_result(field_type) {
+            let value: Option<i64> = self.query_single_value(
+                &aggregate_stmt,
+                &values,
+                mysqlclient_sys::enum_field_types::MYSQL_TYPE_LONGLONG,
+            )?;
+            Ok(value.map(DbValue::Int))
+        } else {
+            let value: Option<f64> = self.query_single_value(
+                &aggregate_stmt,
+                &values,
+                mysqlclient_sys::enum_field_types::MYSQL_TYPE_DOUBLE,
+            )?;
+            Ok(value.map(DbValue::Float))
         }
     }
 
     fn delete_with_cond<T: yoshino_core::types::Schema>(
         &mut self,
         cond: yoshino_core::Cond,
-    ) -> Result<(), yoshino_core::db::DbError> {
+    ) -> Result<usize, yoshino_core::db::DbError> {
         let (cond_clause, cond_values) = MySQLAdaptor::get_cond_expression_code_and_data(cond);
         let delete_stmt = format!(
             "DELETE FROM {} WHERE {};",
             &T::get_schema_name(),
             cond_clause
         );
-        let stmt_cstring = CString::new(delete_stmt.as_str()).unwrap();
-        unsafe {
-            let stmt = mysqlclient_sys::mysql_stmt_init(self.handler);
-            if stmt.is_null() {
-                return Err(DbError(format!("Mysql database error: out of memory.")));
-            }
-            db_stmt_try!(
-                stmt,
-                mysqlclient_sys::mysql_stmt_prepare(
-                    stmt,
-                    stmt_cstring.as_ptr(),
-                    delete_stmt.len() as c_ulong
-                )
-            );
-            let mut bind_list = MySQLBindList::from_boxed_db_data_list(&cond_values);
-            let bind_array = bind_list.binds.as_mut_ptr();
-            db_stmt_try!(
-                stmt,
-                mysqlclient_sys::mysql_stmt_bind_param(stmt, bind_array)
-            );
-            db_stmt_try!(stmt, mysqlclient_sys::mysql_stmt_execute(stmt));
-        }
-        Ok(())
+        let affected_rows = self.execute_stmt(&delete_stmt, &cond_values, |stmt| unsafe {
+            mysqlclient_sys::mysql_stmt_affected_rows(stmt)
+        })?;
+        Ok(affected_rows as usize)
     }
 
-    fn update_with_cond<T: yoshino_core::types::Schema>(
+    fn update_values_with_cond<T: yoshino_core::types::Schema>(
         &mut self,
         cond: yoshino_core::Cond,
-        record: T,
-    ) -> Result<(), yoshino_core::db::DbError> {
+        mut values: Vec<DbValue>,
+    ) -> Result<usize, yoshino_core::db::DbError> {
+        let schema_name = T::get_schema_name();
+        let fields = T::get_fields();
+        let unique_key = T::get_unique_key();
+        MySQLAdaptor::check_unique_key_values(&schema_name, &fields, &unique_key, &values)?;
         let (cond_clause, cond_values) = MySQLAdaptor::get_cond_expression_code_and_data(cond);
-        let update_clause =
-            MySQLAdaptor::get_update_clause_code(&T::get_schema_name(), &T::get_fields());
+        let update_clause = MySQLAdaptor::get_update_clause_code(&schema_name, &fields);
         let update_stmt = format!("{} WHERE {};", update_clause, cond_clause);
-        let stmt_cstring = CString::new(update_stmt.as_str()).unwrap();
-        let mut values = record.get_values();
         values.extend(cond_values);
-        unsafe {
-            let stmt = mysqlclient_sys::mysql_stmt_init(self.handler);
-            if stmt.is_null() {
-                return Err(DbError(format!("Mysql database error: out of memory.")));
+        let affected_rows = self.execute_stmt(&update_stmt, &values, |stmt| unsafe {
+            mysqlclient_sys::mysql_stmt_affected_rows(stmt)
+        })?;
+        Ok(affected_rows as usize)
+    }
+
+    fn begin_transaction(&mut self) -> Result<(), DbError> {
+        if self.transaction_depth == 0 {
+            self.execute_query("START TRANSACTION")?;
+        } else {
+            let savepoint = MySQLAdaptor::get_savepoint_name(self.transaction_depth);
+            self.execute_query(&format!("SAVEPOINT {}", savepoint))?;
+        }
+        self.transaction_depth += 1;
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<(), DbError> {
+        match self.transaction_depth {
+            0 => {
+                return Err(DbError::new(
+                    DbErrorKind::Other,
+                    "MySQL database error: no active transaction".to_string(),
+                ))
+            }
+            1 => self.execute_query("COMMIT")?,
+            depth => {
+                let savepoint = MySQLAdaptor::get_savepoint_name(depth - 1);
+                self.execute_query(&format!("RELEASE SAVEPOINT {}", savepoint))?
+            }
+        }
+        self.transaction_depth -= 1;
+        Ok(())
+    }
+
+    fn rollback(&mut self) -> Result<(), DbError> {
+        match self.transaction_depth {
+            0 => {
+                return Err(DbError::new(
+                    DbErrorKind::Other,
+                    "MySQL database error: no active transaction".to_string(),
+                ))
+            }
+            1 => self.execute_query("ROLLBACK")?,
+            depth => {
+                let savepoint = MySQLAdaptor::get_savepoint_name(depth - 1);
+                self.execute_query(&format!("ROLLBACK TO SAVEPOINT {}", savepoint))?;
+                self.execute_query(&format!("RELEASE SAVEPOINT {}", savepoint))?
             }
-            db_stmt_try!(
-                stmt,
-                mysqlclient_sys::mysql_stmt_prepare(
-                    stmt,
-                    stmt_cstring.as_ptr(),
-                    update_stmt.len() as c_ulong
-                )
-            );
-            let mut bind_list = MySQLBindList::from_boxed_db_data_list(&values);
-            let bind_array = bind_list.binds.as_mut_ptr();
-            db_stmt_try!(
-                stmt,
-                mysqlclient_sys::mysql_stmt_bind_param(stmt, bind_array)
-            );
-            db_stmt_try!(stmt, mysqlclient_sys::mysql_stmt_execute(stmt));
         }
+        self.transaction_depth -= 1;
         Ok(())
     }
 }
 
 /// Database result iterator for MySQL.
-pub struct MySQLResultIterator<T>
+pub struct MySQLResultIterator<'a, T>
 where
     T: yoshino_core::Schema,
 {
     stmt: *mut mysqlclient_sys::st_mysql_stmt,
+    finished: bool,
     fields: Vec<(String, DbDataType)>,
     bind_list: Vec<mysqlclient_sys::MYSQL_BIND>,
     length_list: Vec<u64>,
     is_null_list: Vec<i8>,
+    adaptor: PhantomData<&'a MySQLAdaptor>,
     phantom: PhantomData<T>,
 }
 
-impl<T> MySQLResultIterator<T>
+impl<'a, T> MySQLResultIterator<'a, T>
 where
     T: yoshino_core::Schema,
 {
-    fn new(stmt: *mut mysqlclient_sys::st_mysql_stmt) -> Result<MySQLResultIterator<T>, DbError> {
+    fn new(
+        stmt: *mut mysqlclient_sys::st_mysql_stmt,
+    ) -> Result<MySQLResultIterator<'a, T>, DbError> {
         let fields = T::get_fields();
         let mut length_list = vec![0u64; fields.len()];
         let mut is_null_list = vec![0; fields.len()];
@@ -455,183 +1292,165 @@ where
                 bind_list[i].is_null = is_null_list_ptr.offset(i as isize);
             }
         }
-        unsafe {
-            db_stmt_try!(
-                stmt,
-                mysqlclient_sys::mysql_stmt_bind_result(stmt, bind_list.as_mut_ptr())
-            );
-        }
-        Ok(MySQLResultIterator {
+        // the iterator closes the statement when it's dropped, even if binding fails
+        let mut iterator = MySQLResultIterator {
             stmt,
+            finished: false,
             fields,
             bind_list,
             length_list,
             is_null_list,
+            adaptor: PhantomData,
             phantom: PhantomData,
-        })
+        };
+        unsafe {
+            db_stmt_try!(
+                stmt,
+                mysqlclient_sys::mysql_stmt_bind_result(stmt, iterator.bind_list.as_mut_ptr())
+            );
+        }
+        Ok(iterator)
     }
 
     fn clear_binds(&mut self) {
         let fields_count = self.length_list.len();
         for i in 0..fields_count {
             self.length_list[i] = 0;
+            self.bind_list[i].buffer = ptr::null_mut();
             self.bind_list[i].buffer_type = unsafe { std::mem::zeroed() };
         }
     }
 }
 
-impl<T> Iterator for MySQLResultIterator<T>
+impl<'a, T> Iterator for MySQLResultIterator<'a, T>
 where
     T: yoshino_core::Schema,
 {
-    type Item = T;
+    type Item = Result<T, DbError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let status = unsafe { mysqlclient_sys::mysql_stmt_fetch(self.stmt) };
-        if status == (mysqlclient_sys::MYSQL_NO_DATA as i32) || status == 1 {
+        if self.finished {
             return None;
         }
-        let mut values: Vec<Box<dyn DbData>> = vec![];
+        match self.fetch_row() {
+            // a record that fails to be decoded doesn't end the iteration
+            Ok(Some(values)) => Some(T::create_with_values(values)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(error) => {
+                self.finished = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+impl<'a, T> MySQLResultIterator<'a, T>
+where
+    T: yoshino_core::Schema,
+{
+    /// Fetch the values of the next row. Returns `None` if there is no more row.
+    fn fetch_row(&mut self) -> Result<Option<Vec<DbValue>>, DbError> {
+        let status = unsafe { mysqlclient_sys::mysql_stmt_fetch(self.stmt) };
+        if status == (mysqlclient_sys::MYSQL_NO_DATA as i32) {
+            return Ok(None);
+        }
+        // The result is bound without buffers to get the lengths of columns first, so
+        // MYSQL_DATA_TRUNCATED is expected here. Columns are fetched one by one below.
+        if status == 1 {
+            return Err(unsafe { MySQLAdaptor::get_stmt_error(self.stmt) });
+        }
+        let mut values = Vec::with_capacity(self.fields.len());
         for i in 0..self.fields.len() {
-            match self.fields[i].1 {
-                yoshino_core::db::DbDataType::Int | yoshino_core::db::DbDataType::RowID => {
-                    self.bind_list[i].buffer_length = 1;
-                    unsafe {
-                        let mut buffer: i64 = std::mem::zeroed();
-                        self.bind_list[i].buffer =
-                            (&mut buffer) as *mut i64 as *mut std::ffi::c_void;
-                        self.bind_list[i].buffer_type =
-                            mysqlclient_sys::enum_field_types::MYSQL_TYPE_LONGLONG;
-                        mysqlclient_sys::mysql_stmt_fetch_column(
-                            self.stmt,
-                            self.bind_list.as_mut_ptr().offset(i as isize),
-                            i as u32,
-                            0,
-                        );
-                        values.push(Box::new(buffer));
-                    }
-                }
-                yoshino_core::db::DbDataType::NullableInt => {
-                    if self.is_null_list[i] != 0 {
-                        values.push(Box::<Option<i64>>::new(None));
-                    } else {
-                        self.bind_list[i].buffer_length = 1;
-                        unsafe {
-                            let mut buffer: i64 = std::mem::zeroed();
-                            self.bind_list[i].buffer =
-                                (&mut buffer) as *mut i64 as *mut std::ffi::c_void;
-                            self.bind_list[i].buffer_type =
-                                mysqlclient_sys::enum_field_types::MYSQL_TYPE_LONGLONG;
-                            mysqlclient_sys::mysql_stmt_fetch_column(
-                                self.stmt,
-                                self.bind_list.as_mut_ptr().offset(i as isize),
-                                i as u32,
-                                0,
-                            );
-                            values.push(Box::<Option<i64>>::new(Some(buffer)));
-                        }
-                    }
-                }
-                yoshino_core::db::DbDataType::Text => {
-                    if self.length_list[i] == 0 {
-                        values.push(Box::new("".to_string()));
-                    } else {
-                        let mut buffer: Vec<u8> = vec![0u8; self.length_list[i] as usize];
-                        self.bind_list[i].buffer = buffer.as_mut_ptr() as *mut std::ffi::c_void;
-                        self.bind_list[i].buffer_type =
-                            mysqlclient_sys::enum_field_types::MYSQL_TYPE_STRING;
-                        self.bind_list[i].buffer_length = self.length_list[i];
-                        unsafe {
-                            mysqlclient_sys::mysql_stmt_fetch_column(
-                                self.stmt,
-                                self.bind_list.as_mut_ptr().offset(i as isize),
-                                i as u32,
-                                0,
-                            );
-                        }
-                        values.push(Box::new(String::from_utf8(buffer).unwrap()));
-                    }
-                }
-                yoshino_core::db::DbDataType::NullableText => {
-                    if self.is_null_list[i] != 0 {
-                        values.push(Box::<Option<String>>::new(None));
-                    } else {
-                        let mut buffer: Vec<u8> = vec![0u8; self.length_list[i] as usize];
-                        self.bind_list[i].buffer = buffer.as_mut_ptr() as *mut std::ffi::c_void;
-                        self.bind_list[i].buffer_type =
-                            mysqlclient_sys::enum_field_types::MYSQL_TYPE_STRING;
-                        self.bind_list[i].buffer_length = self.length_list[i];
-                        unsafe {
-                            mysqlclient_sys::mysql_stmt_fetch_column(
-                                self.stmt,
-                                self.bind_list.as_mut_ptr().offset(i as isize),
-                                i as u32,
-                                0,
-                            );
-                        }
-                        values.push(Box::new(Some(String::from_utf8(buffer).unwrap())));
-                    }
+            if self.is_null_list[i] != 0 {
+                values.push(DbValue::Null);
+                continue;
+            }
+            let value = match self.fields[i].1 {
+                field_type if field_type.is_integer() => {
+                    let mut buffer = 0i64;
+                    self.bind_list[i].buffer = (&mut buffer) as *mut i64 as *mut std::ffi::c_void;
+                    self.bind_list[i].buffer_type =
+                        mysqlclient_sys::enum_field_types::MYSQL_TYPE_LONGLONG;
+                    self.fetch_column(i)?;
+                    DbValue::Int(buffer)
                 }
-                yoshino_core::db::DbDataType::Float => {
+                field_type if field_type.is_float() => {
                     let mut buffer = 0f64;
                     self.bind_list[i].buffer = (&mut buffer) as *mut f64 as *mut std::ffi::c_void;
                     self.bind_list[i].buffer_type =
                         mysqlclient_sys::enum_field_types::MYSQL_TYPE_DOUBLE;
-                    self.bind_list[i].buffer_length = self.length_list[i];
-                    unsafe {
-                        mysqlclient_sys::mysql_stmt_fetch_column(
-                            self.stmt,
-                            self.bind_list.as_mut_ptr().offset(i as isize),
-                            i as u32,
-                            0,
-                        );
-                    }
-                    values.push(Box::new(buffer));
-                }
-                yoshino_core::db::DbDataType::Binary => {
-                    if self.length_list[i] == 0 {
-                        values.push(Box::<Vec<u8>>::new(vec![]));
-                    } else {
-                        let mut buffer: Vec<u8> = vec![0u8 ;self.length_list[i] as usize];
-                        self.bind_list[i].buffer = buffer.as_mut_ptr() as *mut std::ffi::c_void;
-                        self.bind_list[i].buffer_type = mysqlclient_sys::enum_field_types::MYSQL_TYPE_BLOB;
-                        self.bind_list[i].buffer_length = self.length_list[i];
-                        unsafe {
-                            mysqlclient_sys::mysql_stmt_fetch_column(
-                                self.stmt,
-                                self.bind_list.as_mut_ptr().offset(i as isize),
-                                i as u32, 0);
-                        }
-                        values.push(Box::new(buffer));
-                    }
+                    self.fetch_column(i)?;
+                    DbValue::Float(buffer)
                 }
-                yoshino_core::db::DbDataType::NullableBinary => {
-                    if self.is_null_list[i] != 0 {
-                        values.push(Box::<Option<Vec<u8>>>::new(None));
-                    } else if self.length_list[i] == 0 {
-                        values.push(Box::<Option<Vec<u8>>>::new(Some(vec![])))
-                    } else {
-                        let mut buffer: Vec<u8> = vec![0u8 ;self.length_list[i] as usize];
-                        self.bind_list[i].buffer = buffer.as_mut_ptr() as *mut std::ffi::c_void;
-                        self.bind_list[i].buffer_type = mysqlclient_sys::enum_field_types::MYSQL_TYPE_BLOB;
-                        self.bind_list[i].buffer_length = self.length_list[i];
-                        unsafe {
-                            mysqlclient_sys::mysql_stmt_fetch_column(
-                                self.stmt,
-                                self.bind_list.as_mut_ptr().offset(i as isize),
-                                i as u32, 0);
-                        }
-                        values.push(Box::new(Some(buffer)));
+                // date times and JSON values are fetched as texts, which are parsed by the fields
+                DbDataType::Text
+                | DbDataType::NullableText
+                | DbDataType::DateTime
+                | DbDataType::NullableDateTime
+                | DbDataType::Date
+                | DbDataType::NullableDate
+                | DbDataType::Json
+                | DbDataType::NullableJson => {
+                    let buffer = self.fetch_bytes_column(
+                        i,
+                        mysqlclient_sys::enum_field_types::MYSQL_TYPE_STRING,
+                    )?;
+                    // text that isn't valid UTF-8 is kept as bytes and rejected by text fields
+                    match String::from_utf8(buffer) {
+                        Ok(text) => DbValue::Text(text),
+                        Err(error) => DbValue::Blob(error.into_bytes()),
                     }
                 }
-            }    
+                _ => DbValue::Blob(self.fetch_bytes_column(
+                    i,
+                    mysqlclient_sys::enum_field_types::MYSQL_TYPE_BLOB,
+                )?),
+            };
+            values.push(value);
         }
         self.clear_binds();
-        Some(T::create_with_values(values))
+        Ok(Some(values))
+    }
+
+    /// Fetch the text or binary column `i` of the current row into a buffer of its length.
+    fn fetch_bytes_column(
+        &mut self,
+        i: usize,
+        buffer_type: mysqlclient_sys::enum_field_types,
+    ) -> Result<Vec<u8>, DbError> {
+        if self.length_list[i] == 0 {
+            return Ok(vec![]);
+        }
+        let mut buffer: Vec<u8> = vec![0u8; self.length_list[i] as usize];
+        self.bind_list[i].buffer = buffer.as_mut_ptr() as *mut std::ffi::c_void;
+        self.bind_list[i].buffer_type = buffer_type;
+        self.bind_list[i].buffer_length = self.length_list[i];
+        self.fetch_column(i)?;
+        Ok(buffer)
+    }
+
+    /// Fetch the column `i` of the current row into its bound buffer. Data that doesn't fit
+    /// into the buffer is reported as an error.
+    fn fetch_column(&mut self, i: usize) -> Result<(), DbError> {
+        let stmt = self.stmt;
+        unsafe {
+            let bind = self.bind_list.as_mut_ptr().add(i);
+            db_stmt_try!(
+                stmt,
+                mysqlclient_sys::mysql_stmt_fetch_column(stmt, bind, i as u32, 0)
+            );
+            if !(*bind).error.is_null() && *(*bind).error != 0 {
+                return Err(MySQLAdaptor::get_truncation_error(&self.fields[i].0));
+            }
+        }
+        Ok(())
     }
 }
 
-impl<T> Drop for MySQLResultIterator<T>
+impl<'a, T> Drop for MySQLResultIterator<'a, T>
 where
     T: yoshino_core::Schema,
 {
@@ -641,3 +1460,6 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod test;
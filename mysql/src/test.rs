@@ -0,0 +1,222 @@
+use yoshino_core::db::{DbDataType, DbErrorKind, DbValue};
+use yoshino_core::Cond;
+
+use crate::MySQLAdaptor;
+
+fn get_test_fields() -> Vec<(String, DbDataType)> {
+    vec![
+        ("id".to_string(), DbDataType::RowID),
+        ("name".to_string(), DbDataType::Text),
+        ("counter".to_string(), DbDataType::Int),
+    ]
+}
+
+#[test]
+fn test_insert_value_stmt_code() {
+    let code = MySQLAdaptor::get_insert_value_stmt_code("y_record", &get_test_fields());
+    assert_eq!(code, "INSERT INTO y_record (id, name, counter) VALUES (?, ?, ?);");
+}
+
+#[test]
+fn test_upsert_stmt_code() {
+    let key = vec!["name".to_string()];
+    let code = MySQLAdaptor::get_upsert_stmt_code("y_record", &get_test_fields(), &key);
+    // the insert id is set to the id of the updated record
+    assert_eq!(
+        code,
+        "INSERT INTO y_record (id, name, counter) VALUES (?, ?, ?) AS new \
+         ON DUPLICATE KEY UPDATE counter = new.counter, id = LAST_INSERT_ID(id);"
+    );
+
+    // a schema without RowID field whose fields are all in the key
+    let fields = vec![("name".to_string(), DbDataType::Text)];
+    let code = MySQLAdaptor::get_upsert_stmt_code("y_tag", &fields, &key);
+    assert_eq!(
+        code,
+        "INSERT INTO y_tag (name) VALUES (?) AS new ON DUPLICATE KEY UPDATE name = new.name;"
+    );
+}
+
+#[test]
+fn test_batch_insert_value_stmt_code() {
+    let code = MySQLAdaptor::get_batch_insert_value_stmt_code("y_record", &get_test_fields(), 2);
+    assert_eq!(
+        code,
+        "INSERT INTO y_record (id, name, counter) VALUES (?, ?, ?), (?, ?, ?);"
+    );
+}
+
+#[test]
+fn test_insert_batch_size() {
+    assert_eq!(MySQLAdaptor::get_insert_batch_size(3), 1000);
+    assert_eq!(MySQLAdaptor::get_insert_batch_size(100), 655);
+    assert_eq!(MySQLAdaptor::get_insert_batch_size(70000), 1);
+    assert_eq!(MySQLAdaptor::get_insert_batch_size(0), 1000);
+}
+
+#[test]
+fn test_limit_code() {
+    assert_eq!(MySQLAdaptor::get_limit_code(Some(10), Some(20)), "LIMIT 10 OFFSET 20");
+    assert_eq!(MySQLAdaptor::get_limit_code(Some(10), None), "LIMIT 10");
+    assert_eq!(
+        MySQLAdaptor::get_limit_code(None, Some(20)),
+        "LIMIT 18446744073709551615 OFFSET 20"
+    );
+    assert_eq!(MySQLAdaptor::get_limit_code(None, None), "");
+}
+
+#[test]
+fn test_in_list_code() {
+    let values = vec![DbValue::Int(1), DbValue::Int(2)];
+    let (code, data) = MySQLAdaptor::get_in_list_code_and_data("value", values.clone(), false);
+    assert_eq!(code, "value IN (?, ?)");
+    assert_eq!(data, values);
+    let (code, data) = MySQLAdaptor::get_in_list_code_and_data("value", vec![], true);
+    assert_eq!(code, "TRUE");
+    assert!(data.is_empty());
+}
+
+#[test]
+fn test_long_in_list_code_is_inlined() {
+    // more values than the limit of placeholders in one statement
+    let values: Vec<DbValue> = (0..70000).map(DbValue::Int).collect();
+    let (code, data) = MySQLAdaptor::get_in_list_code_and_data("value", values, true);
+    let literals: Vec<String> = (0..70000).map(|v: i64| v.to_string()).collect();
+    assert_eq!(code, format!("value NOT IN ({})", literals.join(", ")));
+    assert!(data.is_empty());
+
+    let mut values = vec![
+        DbValue::Null,
+        DbValue::Float(0.5),
+        DbValue::Float(f64::NAN),
+        DbValue::Text("a'\\".to_string()),
+        DbValue::Blob(vec![0x0a, 0xff]),
+    ];
+    values.extend(vec![DbValue::Int(-1); 996]);
+    let (code, data) = MySQLAdaptor::get_in_list_code_and_data("value", values, false);
+    let expected = "value IN (NULL, 0.5, NULL, _utf8mb4 X'61275C', X'0AFF', -1, ";
+    assert!(code.starts_with(expected));
+    assert!(data.is_empty());
+}
+
+#[test]
+fn test_create_table_with_unique_key_code() {
+    let fields = vec![
+        ("id".to_string(), DbDataType::RowID),
+        ("name".to_string(), DbDataType::Text),
+        ("digest".to_string(), DbDataType::NullableBinary),
+        ("note".to_string(), DbDataType::Text),
+    ];
+    let unique_key = vec!["name".to_string(), "digest".to_string()];
+    let code = MySQLAdaptor::get_create_table_stmt_code("y_record", &fields, &unique_key);
+    assert_eq!(
+        code,
+        "CREATE TABLE IF NOT EXISTS y_record (id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY, \
+         name VARCHAR(255) NOT NULL, digest VARBINARY(255), note TEXT NOT NULL, \
+         UNIQUE KEY (name, digest));"
+    );
+}
+
+#[test]
+fn test_unique_key_longer_than_index_limit_is_rejected() {
+    let fields = vec![
+        ("id".to_string(), DbDataType::RowID),
+        ("a".to_string(), DbDataType::Text),
+        ("b".to_string(), DbDataType::Text),
+        ("c".to_string(), DbDataType::NullableText),
+        ("d".to_string(), DbDataType::Binary),
+        ("e".to_string(), DbDataType::Json),
+    ];
+    let key = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+    let check = |unique_key: Vec<String>| {
+        MySQLAdaptor::check_unique_key_length("y_record", &fields, &unique_key)
+    };
+    // 3 * 1020 + 8 bytes
+    assert!(check(key(&["a", "b", "c", "id"])).is_ok());
+    // 3 * 1020 + 255 bytes
+    assert!(check(key(&["a", "b", "c", "d"])).is_err());
+    assert!(check(key(&["a", "e"])).is_err());
+}
+
+#[test]
+fn test_unique_key_values_longer_than_column_are_rejected() {
+    let fields = vec![
+        ("id".to_string(), DbDataType::RowID),
+        ("name".to_string(), DbDataType::Text),
+        ("digest".to_string(), DbDataType::NullableBinary),
+        ("note".to_string(), DbDataType::Text),
+    ];
+    let unique_key = vec!["name".to_string(), "digest".to_string()];
+    let check = |name: String, digest: DbValue, note: String| {
+        let values = vec![DbValue::Null, DbValue::Text(name), digest, DbValue::Text(note)];
+        MySQLAdaptor::check_unique_key_values("y_record", &fields, &unique_key, &values)
+    };
+    // the length of text is counted in characters
+    assert!(check("é".repeat(255), DbValue::Blob(vec![0; 255]), "x".repeat(256)).is_ok());
+    assert!(check("a".repeat(255), DbValue::Null, String::new()).is_ok());
+    let err = check("a".repeat(256), DbValue::Null, String::new()).unwrap_err();
+    assert_eq!(err.kind, DbErrorKind::TypeMismatch);
+    let err = check(String::new(), DbValue::Blob(vec![0; 256]), String::new()).unwrap_err();
+    assert_eq!(err.kind, DbErrorKind::TypeMismatch);
+}
+
+#[test]
+fn test_json_path_cond_code() {
+    let cond = Cond::json_path_equal_to("settings", "$.theme", "dark");
+    let (code, values) = MySQLAdaptor::get_cond_expression_code_and_data(cond);
+    assert_eq!(code, "JSON_EXTRACT(settings, ?) = ?");
+    assert_eq!(values, vec![DbValue::from("$.theme"), DbValue::from("dark")]);
+
+    // booleans are compared as 1 and 0
+    let cond = Cond::json_path_equal_to("settings", "$.visible", 1);
+    let (code, values) = MySQLAdaptor::get_cond_expression_code_and_data(cond);
+    assert_eq!(
+        code,
+        "(CASE JSON_TYPE(JSON_EXTRACT(settings, ?)) \
+         WHEN 'BOOLEAN' THEN (JSON_EXTRACT(settings, ?) = CAST('true' AS JSON)) = ? \
+         ELSE JSON_EXTRACT(settings, ?) = ? END)"
+    );
+    let path = DbValue::from("$.visible");
+    assert_eq!(
+        values,
+        vec![path.clone(), path.clone(), DbValue::Int(1), path, DbValue::Int(1)]
+    );
+}
+
+#[test]
+fn test_regexp_from_glob() {
+    assert_eq!(MySQLAdaptor::get_regexp_from_glob("a*b?c"), "^a.*b.c$");
+    assert_eq!(
+        MySQLAdaptor::get_regexp_from_glob("1.5+(x)|{y}$^\\]"),
+        "^1\\.5\\+\\(x\\)\\|\\{y\\}\\$\\^\\\\\\]$"
+    );
+    assert_eq!(MySQLAdaptor::get_regexp_from_glob("[a-c]x"), "^[a-c]x$");
+    assert_eq!(MySQLAdaptor::get_regexp_from_glob("[^0-9]*"), "^[^0-9].*$");
+    // a leading `]` is a literal in a character class
+    assert_eq!(MySQLAdaptor::get_regexp_from_glob("[]a]"), "^[\\]a]$");
+    assert_eq!(MySQLAdaptor::get_regexp_from_glob("[^]a]"), "^[^\\]a]$");
+    // wildcards are literals in a character class
+    assert_eq!(MySQLAdaptor::get_regexp_from_glob("[*?]"), "^[*?]$");
+    assert_eq!(MySQLAdaptor::get_regexp_from_glob("[\\[&&]"), "^[\\\\\\[\\&\\&]$");
+}
+
+#[test]
+fn test_like_code_has_explicit_escape() {
+    let cond = Cond::starts_with("value", "50%!");
+    let (code, data) = MySQLAdaptor::get_cond_expression_code_and_data(cond);
+    assert_eq!(code, "value LIKE ? ESCAPE '!'");
+    assert_eq!(data, vec![DbValue::from("50!%!!%")]);
+
+    // a raw pattern uses the default escape character of MySQL
+    let (code, _) = MySQLAdaptor::get_cond_expression_code_and_data(Cond::like("value", "a!%"));
+    assert_eq!(code, "value LIKE ?");
+}
+
+#[test]
+fn test_ddl_is_rejected_inside_transaction() {
+    assert!(MySQLAdaptor::check_no_active_transaction(0, "drop a table").is_ok());
+    // DDL statements would commit the active transaction
+    let error = MySQLAdaptor::check_no_active_transaction(2, "drop a table").unwrap_err();
+    assert_eq!(error.kind, DbErrorKind::Other);
+    assert!(error.message.contains("can't drop a table inside a transaction"));
+}
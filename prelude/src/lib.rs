@@ -19,21 +19,48 @@
 //! let mut db =
 //!   SQLiteAdaptor::open("example_db_file")
 //!   .unwrap();                                   // open a SQLite db
-//! db.insert_record(record).unwrap();             // store the record
+//! let row_id = db.insert_record(record).unwrap(); // store the record and get its row id
 //! ```
 //!
 //! The data can be retrieved with:
 //! ```text
 //! for record in adaptor.query_all::<Record>().unwrap() {
+//!     let record = record.unwrap(); // reading a record may fail
 //!     // use the data in record
 //! }
 //! ```
 //!
+//! Conditions can be built from the typed field handles returned by the generated `columns()`
+//! method, so that the field names and the value types are checked by the compiler:
+//! ```text
+//! let cond = Record::columns().reader.gt(10) & Record::columns().title.starts_with("Rust");
+//! for record in adaptor.query_with_cond::<Record>(cond).unwrap() { ... }
+//! ```
+//!
+//! Fields can be marked with `#[yoshino(unique)]` to declare the unique key of the schema.
+//! `upsert_record` inserts a record, or updates the existing record with the same unique key
+//! (or the same row id if there is no unique key).
+//! With MySQL, text and binary fields in the unique key are limited to 255 characters and
+//! bytes respectively, and a longer value is rejected with a `TypeMismatch` error.
+//! The unique key can hold at most three text fields, as InnoDB limits an index to 3072 bytes,
+//! and `upsert_record` requires MySQL 8.0.19 or later.
+//!
+//! Date and time fields (`DateTime<Utc>`, `NaiveDateTime`, `NaiveDate` and `Duration` from
+//! `chrono`) are available with the `chrono` feature.
+//! `Uuid` fields and `UuidKey`, a UUID primary key that can be generated at insert time, are
+//! available with the `uuid` feature.
+//! `Json<T>` fields, which store serde serializable values as JSON texts, are available with
+//! the `json` feature.
+//!
 //! For more usages, please refer to this document and the examples.
 
 pub use yoshino_core;
-pub use yoshino_core::db::{DbAdaptor, DbData, DbDataType, DbError};
-pub use yoshino_core::Cond;
+pub use yoshino_core::db::{Aggregate, DbAdaptor, DbDataType, DbError, DbErrorKind, DbValue};
+pub use yoshino_core::{Column, Cond, Order, Query};
 pub use yoshino_core::Schema;
-pub use yoshino_core::{RowID, NullableIntegerField, NullableTextField, IntegerField, TextField, FloatField, BinaryField, NullableBinaryField};
-pub use yoshino_derive::Schema;
+pub use yoshino_core::{Field, RowID, TimeField};
+#[cfg(feature = "json")]
+pub use yoshino_core::Json;
+#[cfg(feature = "uuid")]
+pub use yoshino_core::UuidKey;
+pub use yoshino_derive::{Schema, YoshinoEnum};
@@ -1,26 +1,44 @@
 //! SQLite adaptor for Project Yoshino
 use libsqlite3_sys::{sqlite3, sqlite3_stmt};
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::marker::PhantomData;
 use std::ops::Drop;
-use std::os::raw::{c_char, c_int};
+use std::os::raw::{c_char, c_int, c_void};
 use std::ptr;
-use yoshino_core::db::{DbAdaptor, DbData, DbDataType, DbError};
-use yoshino_core::Schema;
+use yoshino_core::db::{Aggregate, DbAdaptor, DbDataType, DbError, DbErrorKind, DbValue};
+use yoshino_core::{Order, Query, RowID, Schema};
+
+/// Maximum number of values bound as separate variables in one IN list. Longer lists are bound
+/// as one JSON array. It's the default limit of variables in one statement before SQLite 3.32.
+const MAX_IN_LIST_LENGTH: usize = 999;
 
 pub struct SQLiteAdaptor {
     db_handler: *mut sqlite3,
+    transaction_depth: usize,
 }
 
 macro_rules! db_try {
-    ($e: expr) => {{
+    ($handler: expr, $e: expr) => {{
         {
             let return_value = $e;
             match return_value {
                 libsqlite3_sys::SQLITE_OK | libsqlite3_sys::SQLITE_DONE => {
                     // success, ignore it
                 }
-                error_code => return Err(DbError(format!("SQLite3 error {}", error_code))),
+                error_code => return Err(SQLiteAdaptor::get_error($handler, error_code)),
+            }
+        }
+    }};
+    ($handler: expr, $e: expr, $sql: expr) => {{
+        {
+            let return_value = $e;
+            match return_value {
+                libsqlite3_sys::SQLITE_OK | libsqlite3_sys::SQLITE_DONE => {
+                    // success, ignore it
+                }
+                error_code => {
+                    return Err(SQLiteAdaptor::get_error($handler, error_code).with_sql($sql))
+                }
             }
         }
     }};
@@ -31,15 +49,133 @@ impl SQLiteAdaptor {
         let filename_cstring = CString::new(filename).unwrap();
         let mut db_handler: *mut sqlite3 = ptr::null_mut();
         unsafe {
-            db_try!(libsqlite3_sys::sqlite3_open(
-                filename_cstring.as_ptr(),
-                &mut db_handler
-            ));
+            let result = libsqlite3_sys::sqlite3_open(filename_cstring.as_ptr(), &mut db_handler);
+            if result != libsqlite3_sys::SQLITE_OK {
+                let error = SQLiteAdaptor::get_error(db_handler, result);
+                libsqlite3_sys::sqlite3_close(db_handler);
+                return Err(error);
+            }
+            // report extended result codes, e.g. to tell unique key violations from others
+            libsqlite3_sys::sqlite3_extended_result_codes(db_handler, 1);
         }
-        Ok(SQLiteAdaptor { db_handler })
+        Ok(SQLiteAdaptor {
+            db_handler,
+            transaction_depth: 0,
+        })
     }
 
-    fn get_create_table_stmt_code(schema_name: &str, fields: &Vec<(String, DbDataType)>) -> String {
+    /// Execute one or more SQL statements that don't return any rows.
+    fn execute_sql(&mut self, sql: &str) -> Result<(), DbError> {
+        let sql_cstring = CString::new(sql).unwrap();
+        unsafe {
+            db_try!(
+                self.db_handler,
+                libsqlite3_sys::sqlite3_exec(
+                    self.db_handler,
+                    sql_cstring.as_ptr(),
+                    None,
+                    ptr::null_mut(),
+                    ptr::null_mut()
+                ),
+                sql
+            );
+        }
+        Ok(())
+    }
+
+    /// Run a statement that doesn't return any rows. The statement is finalized whether it
+    /// succeeds or not.
+    fn execute_stmt(&mut self, sql: &str, params: &[DbValue]) -> Result<(), DbError> {
+        self.query_single_value(sql, params, |_| ()).map(|_| ())
+    }
+
+    /// Run a query that returns a single value, which is read by `read_value`.
+    /// Returns `None` if there is no result or the value is null.
+    fn query_single_value<V>(
+        &mut self,
+        sql: &str,
+        params: &[DbValue],
+        read_value: impl FnOnce(*mut sqlite3_stmt) -> V,
+    ) -> Result<Option<V>, DbError> {
+        let sql_cstring = CString::new(sql).unwrap();
+        let mut stmt: *mut sqlite3_stmt = ptr::null_mut();
+        let mut tail = ptr::null();
+        unsafe {
+            db_try!(
+                self.db_handler,
+                libsqlite3_sys::sqlite3_prepare_v2(
+                    self.db_handler,
+                    sql_cstring.as_ptr(),
+                    sql.len() as c_int,
+                    &mut stmt,
+                    &mut tail
+                ),
+                sql
+            );
+        }
+        SQLiteAdaptor::bind_params_to_stmt(stmt, params);
+        unsafe {
+            let value = match libsqlite3_sys::sqlite3_step(stmt) {
+                libsqlite3_sys::SQLITE_ROW => {
+                    if libsqlite3_sys::sqlite3_column_type(stmt, 0) == libsqlite3_sys::SQLITE_NULL {
+                        None
+                    } else {
+                        Some(read_value(stmt))
+                    }
+                }
+                libsqlite3_sys::SQLITE_DONE => None,
+                error_code => {
+                    let error = SQLiteAdaptor::get_error(self.db_handler, error_code).with_sql(sql);
+                    libsqlite3_sys::sqlite3_finalize(stmt);
+                    return Err(error);
+                }
+            };
+            db_try!(
+                self.db_handler,
+                libsqlite3_sys::sqlite3_finalize(stmt),
+                sql
+            );
+            Ok(value)
+        }
+    }
+
+    /// Create a `DbError` from an extended result code and the error message of the connection.
+    fn get_error(db_handler: *mut sqlite3, error_code: c_int) -> DbError {
+        let message = unsafe {
+            let message_ptr = if db_handler.is_null() {
+                libsqlite3_sys::sqlite3_errstr(error_code)
+            } else {
+                libsqlite3_sys::sqlite3_errmsg(db_handler)
+            };
+            CStr::from_ptr(message_ptr).to_string_lossy().into_owned()
+        };
+        let kind = match error_code {
+            libsqlite3_sys::SQLITE_CONSTRAINT_UNIQUE
+            | libsqlite3_sys::SQLITE_CONSTRAINT_PRIMARYKEY => DbErrorKind::UniqueViolation,
+            _ => match error_code & 0xff {
+                libsqlite3_sys::SQLITE_CONSTRAINT => DbErrorKind::ConstraintViolation,
+                libsqlite3_sys::SQLITE_BUSY | libsqlite3_sys::SQLITE_LOCKED => DbErrorKind::Busy,
+                libsqlite3_sys::SQLITE_MISMATCH => DbErrorKind::TypeMismatch,
+                libsqlite3_sys::SQLITE_CANTOPEN => DbErrorKind::ConnectionLost,
+                // SQLite reports syntax errors with the generic error code
+                libsqlite3_sys::SQLITE_ERROR if message.contains("syntax error") => {
+                    DbErrorKind::Syntax
+                }
+                _ => DbErrorKind::Other,
+            },
+        };
+        DbError::new(kind, message).with_code(error_code)
+    }
+
+    fn get_savepoint_name(depth: usize) -> String {
+        format!("yoshino_savepoint_{}", depth)
+    }
+
+    fn get_create_table_stmt_code(
+        schema_name: &str,
+        fields: &Vec<(String, DbDataType)>,
+        unique_key: &[String],
+    ) -> String {
         let mut s = format!("CREATE TABLE IF NOT EXISTS {} (", schema_name);
         for i in 0..fields.len() {
             if i != 0 {
@@ -52,16 +188,71 @@ impl SQLiteAdaptor {
                 DbDataType::NullableInt => "INTEGER",
                 DbDataType::Text => "TEXT NOT NULL",
                 DbDataType::NullableText => "TEXT",
-                DbDataType::Float => "REAL",
+                DbDataType::Float => "REAL NOT NULL",
+                DbDataType::NullableFloat => "REAL",
                 DbDataType::RowID => "INTEGER PRIMARY KEY",
                 DbDataType::Binary => "BLOB NOT NULL",
-                DbDataType::NullableBinary => "BLOB"
+                DbDataType::NullableBinary => "BLOB",
+                DbDataType::Bool
+                | DbDataType::Int8
+                | DbDataType::Int16
+                | DbDataType::Int32
+                | DbDataType::UInt8
+                | DbDataType::UInt16
+                | DbDataType::UInt32 => "INTEGER NOT NULL",
+                DbDataType::NullableBool
+                | DbDataType::NullableInt8
+                | DbDataType::NullableInt16
+                | DbDataType::NullableInt32
+                | DbDataType::NullableUInt8
+                | DbDataType::NullableUInt16
+                | DbDataType::NullableUInt32 => "INTEGER",
+                DbDataType::Float32 => "REAL NOT NULL",
+                DbDataType::NullableFloat32 => "REAL",
+                DbDataType::DateTime | DbDataType::Date => "TEXT NOT NULL",
+                DbDataType::NullableDateTime | DbDataType::NullableDate => "TEXT",
+                DbDataType::Uuid => "BLOB NOT NULL",
+                DbDataType::NullableUuid => "BLOB",
+                DbDataType::UuidKey => "BLOB NOT NULL PRIMARY KEY",
+                DbDataType::Json => "TEXT NOT NULL",
+                DbDataType::NullableJson => "TEXT",
             }
         }
+        if !unique_key.is_empty() {
+            s = format!("{}, UNIQUE ({})", s, unique_key.join(", "));
+        }
         s = s + ");";
         s
     }
 
+    /// The statement to insert a record or update the record with the same `key`, which
+    /// returns the row id of the record.
+    fn get_upsert_stmt_code(
+        schema_name: &str,
+        fields: &Vec<(String, DbDataType)>,
+        key: &[String],
+    ) -> String {
+        let insert_stmt = SQLiteAdaptor::get_insert_value_stmt_code(schema_name, fields);
+        let mut update_fields: Vec<&String> = fields
+            .iter()
+            .filter(|(name, field_type)| *field_type != DbDataType::RowID && !key.contains(name))
+            .map(|(name, _)| name)
+            .collect();
+        if update_fields.is_empty() {
+            update_fields = key.iter().collect();
+        }
+        let assignments: Vec<String> = update_fields
+            .iter()
+            .map(|name| format!("{} = excluded.{}", name, name))
+            .collect();
+        format!(
+            "{} ON CONFLICT ({}) DO UPDATE SET {} RETURNING rowid;",
+            insert_stmt.trim_end_matches(';'),
+            key.join(", "),
+            assignments.join(", ")
+        )
+    }
+
     fn get_insert_value_stmt_code(schema_name: &str, fields: &Vec<(String, DbDataType)>) -> String {
         let mut s = format!("INSERT INTO {} (", schema_name);
         for i in 0..fields.len() {
@@ -107,34 +298,189 @@ impl SQLiteAdaptor {
         s
     }
 
+    /// The condition that the field is in, or not in if `negated`, the list of `values`.
+    ///
+    /// A long list is bound as one JSON array and expanded by `json_each`, so that it takes only
+    /// one of the limited variables of a statement. Blobs can't be put in a JSON array, so a
+    /// long list of blobs is compared by the hex texts of the values.
+    fn get_in_list_stmt_and_params(
+        field_name: &str,
+        values: Vec<DbValue>,
+        negated: bool,
+    ) -> (String, Vec<DbValue>) {
+        if values.is_empty() {
+            // no value is in an empty list
+            return (if negated { "1=1" } else { "1=0" }.to_string(), values);
+        }
+        let operator = if negated { "NOT IN" } else { "IN" };
+        if values.len() <= MAX_IN_LIST_LENGTH {
+            let placeholders = vec!["?"; values.len()].join(",");
+            return (format!("{} {} ({})", field_name, operator, placeholders), values);
+        }
+        let field = if values.iter().any(|value| matches!(value, DbValue::Blob(_))) {
+            format!("hex({})", field_name)
+        } else {
+            field_name.to_owned()
+        };
+        (
+            format!("{} {} (SELECT value FROM json_each(?))", field, operator),
+            vec![DbValue::Text(SQLiteAdaptor::get_json_array_text(&values))],
+        )
+    }
+
+    /// Encode the values as a JSON array. Blobs are encoded as upper case hex texts, and
+    /// non-finite floats, which are stored as null by SQLite, are encoded as null.
+    fn get_json_array_text(values: &[DbValue]) -> String {
+        let items: Vec<String> = values
+            .iter()
+            .map(|value| match value {
+                DbValue::Null => "null".to_owned(),
+                DbValue::Int(v) => v.to_string(),
+                DbValue::Float(v) if v.is_finite() => format!("{:?}", v),
+                DbValue::Float(_) => "null".to_owned(),
+                DbValue::Text(v) => SQLiteAdaptor::get_json_string_text(v),
+                DbValue::Blob(v) => {
+                    let hex: String = v.iter().map(|b| format!("{:02X}", b)).collect();
+                    format!("\"{}\"", hex)
+                }
+            })
+            .collect();
+        format!("[{}]", items.join(","))
+    }
+
+    fn get_json_string_text(value: &str) -> String {
+        let mut s = String::with_capacity(value.len() + 2);
+        s.push('"');
+        for c in value.chars() {
+            match c {
+                '"' => s.push_str("\\\""),
+                '\\' => s.push_str("\\\\"),
+                c if (c as u32) < 0x20 => s.push_str(&format!("\\u{:04x}", c as u32)),
+                c => s.push(c),
+            }
+        }
+        s.push('"');
+        s
+    }
+
     fn get_condition_stmt_and_params(
         cond: yoshino_core::query_cond::Cond,
-    ) -> (String, Vec<Box<dyn DbData>>) {
+    ) -> (String, Vec<DbValue>) {
         use yoshino_core::query_cond::Cond::*;
         match cond {
             IsNull { field_name } => (format!("{} IS NULL", field_name), vec![]),
             IsNotNull { field_name } => (format!("{} IS NOT NULL", field_name), vec![]),
+            In { field_name, values } => {
+                Self::get_in_list_stmt_and_params(&field_name, values, false)
+            }
+            NotIn { field_name, values } => {
+                Self::get_in_list_stmt_and_params(&field_name, values, true)
+            }
+            EqualTo { field_name, value } => (format!("{}=?", field_name), vec![value]),
+            NotEqualTo { field_name, value } => (format!("{}<>?", field_name), vec![value]),
+            GreaterThan { field_name, value } => (format!("{}>?", field_name), vec![value]),
+            LessThan { field_name, value } => (format!("{}<?", field_name), vec![value]),
+            GreaterThanOrEqualTo { field_name, value } => {
+                (format!("{}>=?", field_name), vec![value])
+            }
+            LessThanOrEqualTo { field_name, value } => {
+                (format!("{}<=?", field_name), vec![value])
+            }
             IntegerEqualTo { field_name, value } => {
-                (format!("{}=?", field_name), vec![Box::new(value)])
+                (format!("{}=?", field_name), vec![DbValue::Int(value)])
             }
             IntegerNotEqualTo { field_name, value } => {
-                (format!("{}<>?", field_name), vec![Box::new(value)])
+                (format!("{}<>?", field_name), vec![DbValue::Int(value)])
             }
             IntegerGreaterThan { field_name, value } => {
-                (format!("{}>?", field_name), vec![Box::new(value)])
+                (format!("{}>?", field_name), vec![DbValue::Int(value)])
             }
             IntegerLessThan { field_name, value } => {
-                (format!("{}<?", field_name), vec![Box::new(value)])
+                (format!("{}<?", field_name), vec![DbValue::Int(value)])
             }
             IntegerGreaterThanOrEqualTo { field_name, value } => {
-                (format!("{}>=?", field_name), vec![Box::new(value)])
+                (format!("{}>=?", field_name), vec![DbValue::Int(value)])
             }
             IntegerLessThanOrEqualTo { field_name, value } => {
-                (format!("{}<=?", field_name), vec![Box::new(value)])
+                (format!("{}<=?", field_name), vec![DbValue::Int(value)])
+            }
+            FloatEqualTo {
+                field_name,
+                value,
+                epsilon: None,
+            } => (format!("{}=?", field_name), vec![DbValue::Float(value)]),
+            FloatEqualTo {
+                field_name,
+                value,
+                epsilon: Some(epsilon),
+            } => (
+                format!("{} BETWEEN ? AND ?", field_name),
+                vec![DbValue::Float(value - epsilon), DbValue::Float(value + epsilon)],
+            ),
+            FloatGreaterThan { field_name, value } => {
+                (format!("{}>?", field_name), vec![DbValue::Float(value)])
+            }
+            FloatLessThan { field_name, value } => {
+                (format!("{}<?", field_name), vec![DbValue::Float(value)])
+            }
+            FloatGreaterThanOrEqualTo { field_name, value } => {
+                (format!("{}>=?", field_name), vec![DbValue::Float(value)])
+            }
+            FloatLessThanOrEqualTo { field_name, value } => {
+                (format!("{}<=?", field_name), vec![DbValue::Float(value)])
+            }
+            BinaryEqualTo { field_name, value } => {
+                (format!("{}=?", field_name), vec![DbValue::Blob(value)])
+            }
+            NullSafeEqualTo { field_name, value } => {
+                (format!("{} IS ?", field_name), vec![value])
             }
             TextEqualTo { field_name, value } => {
-                (format!("{}=?", field_name), vec![Box::new(value)])
+                (format!("{}=?", field_name), vec![DbValue::Text(value)])
+            }
+            TextNotEqualTo { field_name, value } => {
+                (format!("{}<>?", field_name), vec![DbValue::Text(value)])
+            }
+            TextGreaterThan { field_name, value } => {
+                (format!("{}>?", field_name), vec![DbValue::Text(value)])
+            }
+            TextLessThan { field_name, value } => {
+                (format!("{}<?", field_name), vec![DbValue::Text(value)])
+            }
+            TextEqualToIgnoreCase { field_name, value } => (
+                format!("lower({})=lower(?)", field_name),
+                vec![DbValue::Text(value)],
+            ),
+            Like {
+                field_name,
+                pattern,
+                escape,
+            } => {
+                let code = match escape {
+                    Some(escape) => format!("{} LIKE ? ESCAPE '{}'", field_name, escape),
+                    None => format!("{} LIKE ?", field_name),
+                };
+                (code, vec![DbValue::Text(pattern)])
             }
+            Glob {
+                field_name,
+                pattern,
+            } => (format!("{} GLOB ?", field_name), vec![DbValue::Text(pattern)]),
+            Before { field_name, value } => (format!("{}<?", field_name), vec![value]),
+            After { field_name, value } => (format!("{}>?", field_name), vec![value]),
+            Between {
+                field_name,
+                start,
+                end,
+            } => (format!("{} BETWEEN ? AND ?", field_name), vec![start, end]),
+            JsonPathEqualTo {
+                field_name,
+                path,
+                value,
+            } => (
+                format!("json_extract({}, ?)=?", field_name),
+                vec![DbValue::Text(path), value],
+            ),
             And { left, right } => {
                 let (left_stmt, left_params) = Self::get_condition_stmt_and_params(*left);
                 let (right_stmt, right_params) = Self::get_condition_stmt_and_params(*right);
@@ -156,57 +502,125 @@ impl SQLiteAdaptor {
         }
     }
 
+    fn get_order_by_clause(order_by: &[(String, Order)]) -> String {
+        let keys: Vec<String> = order_by
+            .iter()
+            .map(|(field_name, order)| match order {
+                Order::Asc => format!("{} ASC", field_name),
+                Order::Desc => format!("{} DESC", field_name),
+            })
+            .collect();
+        format!("ORDER BY {}", keys.join(", "))
+    }
+
+    fn get_limit_clause(limit: Option<u64>, offset: Option<u64>) -> String {
+        match (limit, offset) {
+            (Some(limit), Some(offset)) => format!("LIMIT {} OFFSET {}", limit, offset),
+            (Some(limit), None) => format!("LIMIT {}", limit),
+            // a negative limit means no upper bound in SQLite
+            (None, Some(offset)) => format!("LIMIT -1 OFFSET {}", offset),
+            (None, None) => String::new(),
+        }
+    }
+
+    fn get_aggregate_clause(schema_name: &str, aggregate_expr: &str) -> String {
+        format!("SELECT {} FROM {}", aggregate_expr, schema_name)
+    }
+
+    fn get_aggregate_expr(aggregate: Aggregate, field_name: &str) -> String {
+        match aggregate {
+            Aggregate::Sum => format!("SUM({})", field_name),
+            Aggregate::Min => format!("MIN({})", field_name),
+            Aggregate::Max => format!("MAX({})", field_name),
+            Aggregate::Avg => format!("AVG({})", field_name),
+        }
+    }
+
+    /// Generate the aggregate query statement with an optional condition.
+    fn get_aggregate_stmt_and_params(
+        schema_name: &str,
+        aggregate_expr: &str,
+        cond: Option<yoshino_core::Cond>,
+    ) -> (String, Vec<DbValue>) {
+        let aggregate_clause = SQLiteAdaptor::get_aggregate_clause(schema_name, aggregate_expr);
+        match cond {
+            Some(cond) => {
+                let (cond_stmt, cond_params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
+                (format!("{} WHERE {};", aggregate_clause, cond_stmt), cond_params)
+            }
+            None => (format!("{};", aggregate_clause), vec![]),
+        }
+    }
+
     fn get_delete_clause(schema_name: &str) -> String {
         format!("DELETE FROM {}", schema_name)
     }
 
-    fn bind_params_to_stmt(stmt: *mut sqlite3_stmt, params: &Vec<Box<dyn DbData>>) {
-        for ii in 0..params.len() {
-            let db_data_box = params.get(ii).unwrap();
-            let i = (ii + 1) as i32;
+    fn bind_params_to_stmt(stmt: *mut sqlite3_stmt, params: &[DbValue]) {
+        for (ii, param) in params.iter().enumerate() {
+            let i = (ii + 1) as c_int;
             unsafe {
-                match db_data_box.db_data_type() {
-                    yoshino_core::db::DbDataType::Int => {
-                        let data_ptr = db_data_box.db_data_ptr() as *const i64;
-                        let data_value = *data_ptr;
-                        libsqlite3_sys::sqlite3_bind_int64(stmt, i, data_value);
-                    }
-                    yoshino_core::db::DbDataType::Float => {
-                        let data_ptr = db_data_box.db_data_ptr() as *const f64;
-                        let data_value = *data_ptr;
-                        libsqlite3_sys::sqlite3_bind_double(stmt, i, data_value);
-                    }
-                    yoshino_core::db::DbDataType::NullableInt
-                    | yoshino_core::db::DbDataType::RowID => {
-                        let data_ptr = db_data_box.db_data_ptr() as *const i64;
-                        if data_ptr != ptr::null() {
-                            let data_value = *data_ptr;
-                            libsqlite3_sys::sqlite3_bind_int64(stmt, i, data_value);
-                        } else {
-                            libsqlite3_sys::sqlite3_bind_null(stmt, i);
-                        }
-                    }
-                    yoshino_core::db::DbDataType::Text
-                    | yoshino_core::db::DbDataType::NullableText => {
-                        let data_ptr = db_data_box.db_data_ptr() as *const i8;
-                        let data_len = db_data_box.db_data_len();
-                        libsqlite3_sys::sqlite3_bind_text(
-                            stmt,
-                            i,
-                            data_ptr,
-                            data_len as i32,
-                            libsqlite3_sys::SQLITE_TRANSIENT(),
-                        );
-                    }
-                    yoshino_core::db::DbDataType::Binary | yoshino_core::db::DbDataType::NullableBinary => {
-                        let data_ptr = db_data_box.db_data_ptr();
-                        let data_len = db_data_box.db_data_len();
-                        libsqlite3_sys::sqlite3_bind_blob(stmt, i, data_ptr, data_len as i32, libsqlite3_sys::SQLITE_TRANSIENT());
+                match param {
+                    DbValue::Null => libsqlite3_sys::sqlite3_bind_null(stmt, i),
+                    DbValue::Int(v) => libsqlite3_sys::sqlite3_bind_int64(stmt, i, *v),
+                    DbValue::Float(v) => libsqlite3_sys::sqlite3_bind_double(stmt, i, *v),
+                    DbValue::Text(v) => libsqlite3_sys::sqlite3_bind_text(
+                        stmt,
+                        i,
+                        v.as_ptr() as *const c_char,
+                        v.len() as c_int,
+                        libsqlite3_sys::SQLITE_TRANSIENT(),
+                    ),
+                    DbValue::Blob(v) => libsqlite3_sys::sqlite3_bind_blob(
+                        stmt,
+                        i,
+                        v.as_ptr() as *const c_void,
+                        v.len() as c_int,
+                        libsqlite3_sys::SQLITE_TRANSIENT(),
+                    ),
+                };
+            }
+        }
+    }
+
+    /// Read the value of column `i` of the current row by its storage class.
+    fn read_column(stmt: *mut sqlite3_stmt, i: c_int) -> DbValue {
+        unsafe {
+            match libsqlite3_sys::sqlite3_column_type(stmt, i) {
+                libsqlite3_sys::SQLITE_NULL => DbValue::Null,
+                libsqlite3_sys::SQLITE_INTEGER => {
+                    DbValue::Int(libsqlite3_sys::sqlite3_column_int64(stmt, i))
+                }
+                libsqlite3_sys::SQLITE_FLOAT => {
+                    DbValue::Float(libsqlite3_sys::sqlite3_column_double(stmt, i))
+                }
+                libsqlite3_sys::SQLITE_TEXT => {
+                    let ptr = libsqlite3_sys::sqlite3_column_text(stmt, i);
+                    let bytes = SQLiteAdaptor::copy_column_bytes(stmt, i, ptr);
+                    // text that isn't valid UTF-8 is kept as bytes and rejected by text fields
+                    match String::from_utf8(bytes) {
+                        Ok(text) => DbValue::Text(text),
+                        Err(error) => DbValue::Blob(error.into_bytes()),
                     }
                 }
+                _ => {
+                    let ptr = libsqlite3_sys::sqlite3_column_blob(stmt, i) as *const u8;
+                    DbValue::Blob(SQLiteAdaptor::copy_column_bytes(stmt, i, ptr))
+                }
             }
         }
     }
+
+    /// Copy the bytes of column `i` from the pointer returned by SQLite. The length must be
+    /// read after getting the pointer.
+    unsafe fn copy_column_bytes(stmt: *mut sqlite3_stmt, i: c_int, ptr: *const u8) -> Vec<u8> {
+        let len = libsqlite3_sys::sqlite3_column_bytes(stmt, i) as usize;
+        if ptr.is_null() {
+            vec![]
+        } else {
+            std::slice::from_raw_parts(ptr, len).to_vec()
+        }
+    }
 }
 
 impl Drop for SQLiteAdaptor {
@@ -217,124 +631,85 @@ impl Drop for SQLiteAdaptor {
     }
 }
 
-pub struct SQLiteRowIterator<T: Schema + 'static> {
+/// Iterator over the records of a query. It borrows the adaptor, so the adaptor can't be
+/// dropped or used for other statements while the iterator is alive.
+///
+/// ```
+/// use yoshino_core::db::DbAdaptor;
+/// use yoshino_core::RowID;
+/// use yoshino_derive::Schema;
+/// use yoshino_sqlite::SQLiteAdaptor;
+///
+/// #[derive(Schema)]
+/// struct Item {
+///     pub id: RowID,
+///     pub name: String,
+/// }
+///
+/// let mut adaptor = SQLiteAdaptor::open(":memory:").unwrap();
+/// adaptor.create_table_for_schema::<Item>().unwrap();
+/// let items = adaptor.query_all::<Item>().unwrap();
+/// assert_eq!(items.count(), 0);
+/// drop(adaptor);
+/// ```
+///
+/// Using the iterator after the adaptor is dropped doesn't compile:
+///
+/// ```compile_fail
+/// use yoshino_core::db::DbAdaptor;
+/// use yoshino_core::RowID;
+/// use yoshino_derive::Schema;
+/// use yoshino_sqlite::SQLiteAdaptor;
+///
+/// #[derive(Schema)]
+/// struct Item {
+///     pub id: RowID,
+///     pub name: String,
+/// }
+///
+/// let mut adaptor = SQLiteAdaptor::open(":memory:").unwrap();
+/// adaptor.create_table_for_schema::<Item>().unwrap();
+/// let items = adaptor.query_all::<Item>().unwrap();
+/// drop(adaptor);
+/// assert_eq!(items.count(), 0);
+/// ```
+pub struct SQLiteRowIterator<'a, T: Schema + 'static> {
+    db_handler: *mut sqlite3,
     stmt: *mut sqlite3_stmt,
+    finished: bool,
+    adaptor: PhantomData<&'a SQLiteAdaptor>,
     phantom: PhantomData<T>,
 }
 
-impl<T: Schema> Iterator for SQLiteRowIterator<T> {
-    type Item = T;
+impl<'a, T: Schema> Iterator for SQLiteRowIterator<'a, T> {
+    type Item = Result<T, DbError>;
     fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            // stepping a finished statement would run the query again
+            return None;
+        }
         let r = unsafe { libsqlite3_sys::sqlite3_step(self.stmt) };
         match r {
-            libsqlite3_sys::SQLITE_DONE => None,
+            libsqlite3_sys::SQLITE_DONE => {
+                self.finished = true;
+                None
+            }
             libsqlite3_sys::SQLITE_ROW => {
-                let mut values: Vec<Box<dyn DbData>> = vec![];
-                let fields = T::get_fields();
-                for i in 0..fields.len() {
-                    let (_, field_type) = fields.get(i).unwrap();
-                    match field_type {
-                        DbDataType::NullableInt => {
-                            let type_code =
-                                unsafe { libsqlite3_sys::sqlite3_column_type(self.stmt, i as i32) };
-                            let v = match type_code {
-                                libsqlite3_sys::SQLITE_INTEGER => Some(unsafe {
-                                    libsqlite3_sys::sqlite3_column_int64(self.stmt, i as i32)
-                                }),
-                                _ => None,
-                            };
-                            values.push(Box::new(v));
-                        }
-                        DbDataType::Int => {
-                            let v = unsafe {
-                                libsqlite3_sys::sqlite3_column_int64(self.stmt, i as i32) as i64
-                            };
-                            values.push(Box::new(v));
-                        }
-                        DbDataType::Float => {
-                            let v = unsafe {
-                                libsqlite3_sys::sqlite3_column_double(self.stmt, i as i32) as f64
-                            };
-                            values.push(Box::new(v));
-                        }
-                        DbDataType::RowID => {
-                            let v = unsafe {
-                                libsqlite3_sys::sqlite3_column_int64(self.stmt, i as i32) as i64
-                            };
-                            values.push(Box::new(yoshino_core::RowID::ID(v)))
-                        }
-                        DbDataType::Text => {
-                            let v = unsafe { 
-                                let str_ptr = libsqlite3_sys::sqlite3_column_text(self.stmt, i as i32) as *const c_char;
-                                let str_len = libsqlite3_sys::sqlite3_column_bytes(self.stmt, i as i32) as usize;
-                                let mut buffer = Vec::<u8>::with_capacity(str_len);
-                                for i in 0..str_len {
-                                    let d = *(str_ptr.offset(i as isize)) as u8;
-                                    buffer.push(d);
-                                }
-                                String::from_utf8(buffer).unwrap()
-                            };
-                            values.push(Box::new(v));
-                        }
-                        DbDataType::NullableText => {
-                            let v = unsafe { 
-                                let str_ptr = libsqlite3_sys::sqlite3_column_text(self.stmt, i as i32) as *const c_char;
-                                let str_len = libsqlite3_sys::sqlite3_column_bytes(self.stmt, i as i32) as usize;
-                                if str_ptr.is_null() {
-                                    None
-                                } else {
-                                    let mut buffer = Vec::<u8>::with_capacity(str_len);
-                                    for i in 0..str_len {
-                                    let d = *(str_ptr.offset(i as isize)) as u8;
-                                        buffer.push(d);
-                                    }
-                                    Some(String::from_utf8(buffer).unwrap())
-                                }
-                            };
-                            values.push(Box::new(v));
-                        }
-                        DbDataType::Binary => {
-                            let v = unsafe {
-                                let ptr = libsqlite3_sys::sqlite3_column_blob(self.stmt, i as i32) as *const u8;
-                                let len = libsqlite3_sys::sqlite3_column_bytes(self.stmt, i as i32) as usize; 
-                                let mut buffer = Vec::<u8>::new();
-                                for i in 0..len {
-                                    let d = *(ptr.offset(i as isize));
-                                    buffer.push(d);
-                                }
-                                buffer
-                            };
-                            values.push(Box::new(v));
-                        }
-                        DbDataType::NullableBinary => {
-                            let v = unsafe {
-                                let ptr = libsqlite3_sys::sqlite3_column_blob(self.stmt, i as i32) as *const u8;
-                                let len = libsqlite3_sys::sqlite3_column_bytes(self.stmt, i as i32) as usize; 
-                                if ptr.is_null() {
-                                    None
-                                }else {
-                                    let mut buffer = Vec::<u8>::new();
-                                    for i in 0..len {
-                                        let d = *(ptr.offset(i as isize));
-                                        buffer.push(d);
-                                    }
-                                    Some(buffer)
-                                }
-                            };
-                            values.push(Box::new(v));
-                        }
-
-                    };
-                }
-                let d = T::create_with_values(values);
-                Some(d)
+                let values = (0..T::get_fields().len())
+                    .map(|i| SQLiteAdaptor::read_column(self.stmt, i as c_int))
+                    .collect();
+                // a record that fails to be decoded doesn't end the iteration
+                Some(T::create_with_values(values))
+            }
+            error_code => {
+                self.finished = true;
+                Some(Err(SQLiteAdaptor::get_error(self.db_handler, error_code)))
             }
-            _ => None,
         }
     }
 }
 
-impl<T: Schema> Drop for SQLiteRowIterator<T> {
+impl<'a, T: Schema> Drop for SQLiteRowIterator<'a, T> {
     fn drop(&mut self) {
         unsafe {
             libsqlite3_sys::sqlite3_finalize(self.stmt);
@@ -343,158 +718,292 @@ impl<T: Schema> Drop for SQLiteRowIterator<T> {
 }
 
 impl DbAdaptor for SQLiteAdaptor {
-    type Iterator<T: Schema> = SQLiteRowIterator<T>;
+    type Iterator<'a, T: Schema> = SQLiteRowIterator<'a, T>;
 
     fn create_table_for_schema<T: Schema>(&mut self) -> Result<(), DbError> {
         let schema_name = T::get_schema_name();
         let fields = T::get_fields();
-        let create_table_stmt = SQLiteAdaptor::get_create_table_stmt_code(&schema_name, &fields);
-        let stmt_cstring = CString::new(create_table_stmt.as_str()).unwrap();
-        let mut stmt: *mut sqlite3_stmt = ptr::null_mut();
-        let mut tail = ptr::null();
-        unsafe {
-            // TODO: check result value and generate errors
-            db_try!(libsqlite3_sys::sqlite3_prepare_v2(
-                self.db_handler,
-                stmt_cstring.as_ptr(),
-                create_table_stmt.len() as c_int,
-                &mut stmt,
-                &mut tail
-            ));
-            db_try!(libsqlite3_sys::sqlite3_step(stmt));
-            db_try!(libsqlite3_sys::sqlite3_finalize(stmt));
-        };
-        Ok(())
+        let create_table_stmt = SQLiteAdaptor::get_create_table_stmt_code(
+            &schema_name,
+            &fields,
+            &T::get_unique_key(),
+        );
+        self.execute_stmt(&create_table_stmt, &[])
+    }
+
+    fn insert_values<T: Schema>(&mut self, values: Vec<DbValue>) -> Result<RowID, DbError> {
+        let schema_name = T::get_schema_name();
+        let fields = T::get_fields();
+        let insert_record_stmt = SQLiteAdaptor::get_insert_value_stmt_code(&schema_name, &fields);
+        self.execute_stmt(&insert_record_stmt, &values)?;
+        if T::get_row_id_field_name().is_none() {
+            // the last insert row id is the hidden rowid of the table
+            return Ok(RowID::NEW);
+        }
+        let row_id = unsafe { libsqlite3_sys::sqlite3_last_insert_rowid(self.db_handler) };
+        Ok(RowID::ID(row_id))
     }
 
-    fn insert_record<T: Schema>(&mut self, record: T) -> Result<(), DbError> {
+    fn insert_records<T: Schema, I: IntoIterator<Item = T>>(
+        &mut self,
+        records: I,
+    ) -> Result<usize, DbError> {
         let schema_name = T::get_schema_name();
         let fields = T::get_fields();
         let insert_record_stmt = SQLiteAdaptor::get_insert_value_stmt_code(&schema_name, &fields);
         let stmt_cstring = CString::new(insert_record_stmt.as_str()).unwrap();
-        let mut stmt: *mut sqlite3_stmt = ptr::null_mut();
-        let mut tail = ptr::null();
+        self.transaction(|tx| {
+            let mut stmt: *mut sqlite3_stmt = ptr::null_mut();
+            let mut tail = ptr::null();
+            unsafe {
+                db_try!(
+                    tx.db_handler,
+                    libsqlite3_sys::sqlite3_prepare_v2(
+                        tx.db_handler,
+                        stmt_cstring.as_ptr(),
+                        insert_record_stmt.len() as c_int,
+                        &mut stmt,
+                        &mut tail
+                    ),
+                    &insert_record_stmt
+                );
+            }
+            // the statement is prepared once and reset after inserting each record
+            let result = (|| {
+                let mut count = 0;
+                for mut record in records {
+                    record.generate_keys();
+                    let params = record.get_values();
+                    SQLiteAdaptor::bind_params_to_stmt(stmt, &params);
+                    unsafe {
+                        db_try!(
+                            tx.db_handler,
+                            libsqlite3_sys::sqlite3_step(stmt),
+                            &insert_record_stmt
+                        );
+                        db_try!(
+                            tx.db_handler,
+                            libsqlite3_sys::sqlite3_reset(stmt),
+                            &insert_record_stmt
+                        );
+                    }
+                    count += 1;
+                }
+                Ok(count)
+            })();
+            unsafe {
+                libsqlite3_sys::sqlite3_finalize(stmt);
+            }
+            result
+        })
+    }
+
+    fn upsert_record<T: Schema>(&mut self, mut record: T) -> Result<RowID, DbError> {
+        let schema_name = T::get_schema_name();
+        let key = match T::get_upsert_key() {
+            Some(key) => key,
+            None => {
+                return Err(DbError::new(
+                    DbErrorKind::Other,
+                    format!(
+                        "SQLite3 error: {} has neither unique key nor primary key field",
+                        schema_name
+                    ),
+                ))
+            }
+        };
+        let upsert_stmt = SQLiteAdaptor::get_upsert_stmt_code(&schema_name, &T::get_fields(), &key);
+        record.generate_keys();
         let params = record.get_values();
-        unsafe {
-            db_try!(libsqlite3_sys::sqlite3_prepare_v2(
-                self.db_handler,
-                stmt_cstring.as_ptr(),
-                insert_record_stmt.len() as c_int,
-                &mut stmt,
-                &mut tail
-            ));
+        let row_id = self.query_single_value(&upsert_stmt, &params, |stmt| unsafe {
+            libsqlite3_sys::sqlite3_column_int64(stmt, 0)
+        })?;
+        if T::get_row_id_field_name().is_none() {
+            // the returned rowid is the hidden rowid of the table
+            return Ok(RowID::NEW);
         }
-        SQLiteAdaptor::bind_params_to_stmt(stmt, &params);
-        unsafe {
-            db_try!(libsqlite3_sys::sqlite3_step(stmt));
-            db_try!(libsqlite3_sys::sqlite3_finalize(stmt));
-        }
-        Ok(())
+        Ok(row_id.map_or(RowID::NEW, RowID::ID))
     }
 
-    fn query_all<T: Schema>(&mut self) -> Result<SQLiteRowIterator<T>, DbError> {
+    fn query<T: Schema>(
+        &mut self,
+        query: Query<T>,
+    ) -> Result<SQLiteRowIterator<'_, T>, DbError> {
         let schema_name = T::get_schema_name();
         let fields = T::get_fields();
-        let query_stmt = SQLiteAdaptor::get_query_clause(&schema_name, &fields) + ";";
+        let mut query_stmt = SQLiteAdaptor::get_query_clause(&schema_name, &fields);
+        let mut query_params = vec![];
+        if let Some(cond) = query.cond {
+            let (cond_stmt, cond_params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
+            query_stmt = format!("{} WHERE {}", query_stmt, cond_stmt);
+            query_params = cond_params;
+        }
+        if !query.order_by.is_empty() {
+            let order_by_clause = SQLiteAdaptor::get_order_by_clause(&query.order_by);
+            query_stmt = format!("{} {}", query_stmt, order_by_clause);
+        }
+        if query.limit.is_some() || query.offset.is_some() {
+            let limit_clause = SQLiteAdaptor::get_limit_clause(query.limit, query.offset);
+            query_stmt = format!("{} {}", query_stmt, limit_clause);
+        }
+        query_stmt += ";";
         let stmt_cstring = CString::new(query_stmt.as_str()).unwrap();
         let mut stmt: *mut sqlite3_stmt = ptr::null_mut();
         let mut tail = ptr::null();
         unsafe {
-            // TODO: check result value and generate errors
-            db_try!(libsqlite3_sys::sqlite3_prepare_v2(
+            db_try!(
                 self.db_handler,
-                stmt_cstring.as_ptr(),
-                query_stmt.len() as c_int,
-                &mut stmt,
-                &mut tail
-            ));
-        };
+                libsqlite3_sys::sqlite3_prepare_v2(
+                    self.db_handler,
+                    stmt_cstring.as_ptr(),
+                    query_stmt.len() as c_int,
+                    &mut stmt,
+                    &mut tail
+                ),
+                &query_stmt
+            );
+        }
+        SQLiteAdaptor::bind_params_to_stmt(stmt, &query_params);
         Ok(SQLiteRowIterator {
+            db_handler: self.db_handler,
             stmt,
+            finished: false,
+            adaptor: PhantomData,
             phantom: PhantomData,
         })
     }
 
-    fn query_with_cond<T: Schema>(
+    fn drop_table_for_schema<T: Schema>(&mut self) -> Result<(), DbError> {
+        self.execute_sql(&format!("DROP TABLE IF EXISTS {};", T::get_schema_name()))
+    }
+
+    fn truncate_table_for_schema<T: Schema>(&mut self) -> Result<(), DbError> {
+        // SQLite has no TRUNCATE statement, while DELETE without WHERE is optimized to it.
+        self.execute_sql(&format!("DELETE FROM {};", T::get_schema_name()))
+    }
+
+    fn table_exists_for_schema<T: Schema>(&mut self) -> Result<bool, DbError> {
+        let params = vec![DbValue::Text(T::get_schema_name())];
+        let count = self.query_single_value(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?;",
+            &params,
+            |stmt| unsafe { libsqlite3_sys::sqlite3_column_int64(stmt, 0) },
+        )?;
+        Ok(count.unwrap_or(0) > 0)
+    }
+
+    fn count<T: Schema>(&mut self, cond: Option<yoshino_core::Cond>) -> Result<i64, DbError> {
+        let schema_name = T::get_schema_name();
+        let (count_stmt, params) =
+            SQLiteAdaptor::get_aggregate_stmt_and_params(&schema_name, "COUNT(*)", cond);
+        let count = self.query_single_value(&count_stmt, &params, |stmt| unsafe {
+            libsqlite3_sys::sqlite3_column_int64(stmt, 0)
+        })?;
+        Ok(count.unwrap_or(0))
+    }
+
+    fn aggregate<T: Schema>(
         &mut self,
-        cond: yoshino_core::query_cond::Cond,
-    ) -> Result<SQLiteRowIterator<T>, DbError> {
+        aggregate: Aggregate,
+        field_name: &str,
+        cond: Option<yoshino_core::Cond>,
+    ) -> Result<Option<DbValue>, DbError> {
         let schema_name = T::get_schema_name();
-        let fields = T::get_fields();
-        let query_stmt = SQLiteAdaptor::get_query_clause(&schema_name, &fields);
-        let (cond_stmt, cond_params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
-        let query_where_cond_stmt = format!("{} WHERE {};", query_stmt, cond_stmt);
-        let stmt_cstring = CString::new(query_where_cond_stmt.as_str()).unwrap();
-        let mut stmt: *mut sqlite3_stmt = ptr::null_mut();
-        let mut tail = ptr::null();
-        unsafe {
-            db_try!(libsqlite3_sys::sqlite3_prepare_v2(
-                self.db_handler,
-                stmt_cstring.as_ptr(),
-                query_where_cond_stmt.len() as c_int,
-                &mut stmt,
-                &mut tail
-            ));
-            SQLiteAdaptor::bind_params_to_stmt(stmt, &cond_params);
-        }
-        Ok(SQLiteRowIterator {
-            stmt,
-            phantom: PhantomData,
+        let field_type = match T::get_field_type(field_name) {
+            Some(field_type) if field_type.is_numeric() => field_type,
+            _ => {
+                return Err(DbError::new(
+                    DbErrorKind::TypeMismatch,
+                    format!(
+                        "SQLite3 error: {} is not a numeric field of {}",
+                        field_name, schema_name
+                    ),
+                ))
+            }
+        };
+        let aggregate_expr = SQLiteAdaptor::get_aggregate_expr(aggregate, field_name);
+        let (aggregate_stmt, params) =
+            SQLiteAdaptor::get_aggregate_stmt_and_params(&schema_name, &aggregate_expr, cond);
+        self.query_single_value(&aggregate_stmt, &params, |stmt| unsafe {
+            if aggregate.has_integer_result(field_type) {
+                DbValue::Int(libsqlite3_sys::sqlite3_column_int64(stmt, 0))
+            } else {
+                DbValue::Float(libsqlite3_sys::sqlite3_column_double(stmt, 0))
+            }
         })
     }
 
-    fn delete_with_cond<T: Schema>(&mut self, cond: yoshino_core::Cond) -> Result<(), DbError> {
+    fn delete_with_cond<T: Schema>(&mut self, cond: yoshino_core::Cond) -> Result<usize, DbError> {
         let schema_name = T::get_schema_name();
         let delete_clause = SQLiteAdaptor::get_delete_clause(&schema_name);
         let (cond_stmt, cond_params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
         let delete_where_cond_stmt = format!("{} WHERE {};", delete_clause, cond_stmt);
-        let stmt_cstring = CString::new(delete_where_cond_stmt.as_str()).unwrap();
-        let mut stmt: *mut sqlite3_stmt = ptr::null_mut();
-        let mut tail = ptr::null();
-        unsafe {
-            db_try!(libsqlite3_sys::sqlite3_prepare_v2(
-                self.db_handler,
-                stmt_cstring.as_ptr(),
-                delete_where_cond_stmt.len() as c_int,
-                &mut stmt,
-                &mut tail
-            ));
-            SQLiteAdaptor::bind_params_to_stmt(stmt, &cond_params);
-            db_try!(libsqlite3_sys::sqlite3_step(stmt));
-            db_try!(libsqlite3_sys::sqlite3_finalize(stmt));
-        }
-        Ok(())
+        self.execute_stmt(&delete_where_cond_stmt, &cond_params)?;
+        Ok(unsafe { libsqlite3_sys::sqlite3_changes(self.db_handler) } as usize)
     }
 
-    fn update_with_cond<T: Schema>(
+    fn update_values_with_cond<T: Schema>(
         &mut self,
         cond: yoshino_core::Cond,
-        record: T,
-    ) -> Result<(), DbError> {
+        mut values: Vec<DbValue>,
+    ) -> Result<usize, DbError> {
         let schema_name = T::get_schema_name();
         let fields = T::get_fields();
         let update_clause = SQLiteAdaptor::get_update_clause(&schema_name, &fields);
         let (cond_stmt, cond_params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
         let update_where_cond_stmt = format!("{} WHERE {};", update_clause, cond_stmt);
-        let mut update_stmt_params = record.get_values();
-        update_stmt_params.extend(cond_params);
+        values.extend(cond_params);
+        self.execute_stmt(&update_where_cond_stmt, &values)?;
+        Ok(unsafe { libsqlite3_sys::sqlite3_changes(self.db_handler) } as usize)
+    }
 
-        let stmt_cstring = CString::new(update_where_cond_stmt.as_str()).unwrap();
-        let mut stmt: *mut sqlite3_stmt = ptr::null_mut();
-        let mut tail = ptr::null();
+    fn begin_transaction(&mut self) -> Result<(), DbError> {
+        if self.transaction_depth == 0 {
+            self.execute_sql("BEGIN;")?;
+        } else {
+            let savepoint = SQLiteAdaptor::get_savepoint_name(self.transaction_depth);
+            self.execute_sql(&format!("SAVEPOINT {};", savepoint))?;
+        }
+        self.transaction_depth += 1;
+        Ok(())
+    }
 
-        unsafe {
-            db_try!(libsqlite3_sys::sqlite3_prepare_v2(
-                self.db_handler,
-                stmt_cstring.as_ptr(),
-                update_where_cond_stmt.len() as c_int,
-                &mut stmt,
-                &mut tail
-            ));
-            SQLiteAdaptor::bind_params_to_stmt(stmt, &update_stmt_params);
-            db_try!(libsqlite3_sys::sqlite3_step(stmt));
-            db_try!(libsqlite3_sys::sqlite3_finalize(stmt));
+    fn commit(&mut self) -> Result<(), DbError> {
+        match self.transaction_depth {
+            0 => {
+                return Err(DbError::new(
+                    DbErrorKind::Other,
+                    "SQLite3 error: no active transaction".to_string(),
+                ))
+            }
+            1 => self.execute_sql("COMMIT;")?,
+            depth => {
+                let savepoint = SQLiteAdaptor::get_savepoint_name(depth - 1);
+                self.execute_sql(&format!("RELEASE SAVEPOINT {};", savepoint))?
+            }
+        }
+        self.transaction_depth -= 1;
+        Ok(())
+    }
+
+    fn rollback(&mut self) -> Result<(), DbError> {
+        match self.transaction_depth {
+            0 => {
+                return Err(DbError::new(
+                    DbErrorKind::Other,
+                    "SQLite3 error: no active transaction".to_string(),
+                ))
+            }
+            1 => self.execute_sql("ROLLBACK;")?,
+            depth => {
+                let savepoint = SQLiteAdaptor::get_savepoint_name(depth - 1);
+                self.execute_sql(&format!(
+                    "ROLLBACK TO SAVEPOINT {}; RELEASE SAVEPOINT {};",
+                    savepoint, savepoint
+                ))?
+            }
         }
+        self.transaction_depth -= 1;
         Ok(())
     }
 }
@@ -14,10 +14,31 @@ const TEST_TABLE_NAME: &str = "test_table_name";
 
 #[test]
 fn test_create_table_stmt_creation() {
-    let stmt = SQLiteAdaptor::get_create_table_stmt_code(TEST_TABLE_NAME, &get_test_fields());
+    let stmt = SQLiteAdaptor::get_create_table_stmt_code(TEST_TABLE_NAME, &get_test_fields(), &[]);
     assert_eq!(stmt, "CREATE TABLE IF NOT EXISTS test_table_name (row_id INTEGER PRIMARY KEY, name TEXT NOT NULL, desc TEXT, counter INTEGER NOT NULL);");
 }
 
+#[test]
+fn test_create_table_with_unique_key_stmt_creation() {
+    let unique_key = vec!["name".to_string(), "desc".to_string()];
+    let stmt =
+        SQLiteAdaptor::get_create_table_stmt_code(TEST_TABLE_NAME, &get_test_fields(), &unique_key);
+    assert_eq!(stmt, "CREATE TABLE IF NOT EXISTS test_table_name (row_id INTEGER PRIMARY KEY, name TEXT NOT NULL, desc TEXT, counter INTEGER NOT NULL, UNIQUE (name, desc));");
+}
+
+#[test]
+fn test_upsert_stmt_creation() {
+    let stmt = SQLiteAdaptor::get_upsert_stmt_code(
+        TEST_TABLE_NAME,
+        &get_test_fields(),
+        &["name".to_string()],
+    );
+    assert_eq!(
+        stmt,
+        "INSERT INTO test_table_name (row_id, name, desc, counter) VALUES (?1, ?2, ?3, ?4) ON CONFLICT (name) DO UPDATE SET desc = excluded.desc, counter = excluded.counter RETURNING rowid;"
+    );
+}
+
 #[test]
 fn test_insert_value_stmt_creation() {
     let stmt = SQLiteAdaptor::get_insert_value_stmt_code(TEST_TABLE_NAME, &get_test_fields());
@@ -45,16 +66,56 @@ fn test_update_clause() {
     );
 }
 
+#[test]
+fn test_aggregate_stmt() {
+    use yoshino_core::{db::Aggregate, Cond};
+    let aggregate_expr = SQLiteAdaptor::get_aggregate_expr(Aggregate::Avg, "counter");
+    let (stmt, params) = SQLiteAdaptor::get_aggregate_stmt_and_params(
+        TEST_TABLE_NAME,
+        &aggregate_expr,
+        Some(Cond::is_null("desc")),
+    );
+    assert_eq!(
+        stmt,
+        "SELECT AVG(counter) FROM test_table_name WHERE desc IS NULL;"
+    );
+    assert!(params.is_empty());
+}
+
+#[test]
+fn test_order_by_clause() {
+    use yoshino_core::Order;
+    let order_by = vec![
+        ("counter".to_string(), Order::Desc),
+        ("name".to_string(), Order::Asc),
+    ];
+    let stmt = SQLiteAdaptor::get_order_by_clause(&order_by);
+    assert_eq!(stmt, "ORDER BY counter DESC, name ASC");
+}
+
+#[test]
+fn test_limit_clause() {
+    assert_eq!(SQLiteAdaptor::get_limit_clause(Some(20), None), "LIMIT 20");
+    assert_eq!(
+        SQLiteAdaptor::get_limit_clause(Some(20), Some(40)),
+        "LIMIT 20 OFFSET 40"
+    );
+    assert_eq!(
+        SQLiteAdaptor::get_limit_clause(None, Some(40)),
+        "LIMIT -1 OFFSET 40"
+    );
+}
+
 mod cond_parsing_test {
     use crate::SQLiteAdaptor;
-    use yoshino_core::{db::DbData, Cond};
+    use yoshino_core::{db::DbValue, Cond};
 
     #[test]
     fn test_int_eq_cond() {
         let cond = Cond::integer_equal_to("value", 0xff);
         let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
         assert_eq!(clause, "value=?");
-        assert_eq!(i64::from_boxed_db_data(&params[0]), 0xff);
+        assert_eq!(params[0], DbValue::Int(0xff));
     }
 
     #[test]
@@ -62,7 +123,75 @@ mod cond_parsing_test {
         let cond = Cond::integer_not_equal_to("value", 0xff);
         let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
         assert_eq!(clause, "value<>?");
-        assert_eq!(i64::from_boxed_db_data(&params[0]), 0xff);
+        assert_eq!(params[0], DbValue::Int(0xff));
+    }
+
+    #[test]
+    fn test_generic_eq_cond() {
+        let cond = Cond::equal_to("value", 1.5);
+        let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
+        assert_eq!(clause, "value=?");
+        assert_eq!(params, vec![DbValue::Float(1.5)]);
+        let cond = Cond::not_equal_to("value", true);
+        let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
+        assert_eq!(clause, "value<>?");
+        assert_eq!(params, vec![DbValue::Int(1)]);
+    }
+
+    #[test]
+    fn test_like_cond_escapes_wildcards() {
+        let cond = Cond::starts_with("value", "50%_off!\\");
+        let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
+        assert_eq!(clause, "value LIKE ? ESCAPE '!'");
+        assert_eq!(params[0], DbValue::from("50!%!_off!!\\%"));
+    }
+
+    #[test]
+    fn test_raw_like_cond_has_no_escape() {
+        let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(Cond::like("v", "a!%"));
+        assert_eq!(clause, "v LIKE ?");
+        assert_eq!(params[0], DbValue::from("a!%"));
+    }
+
+    #[test]
+    fn test_in_cond() {
+        let cond = Cond::integer_in("value", &[1, 2, 3]);
+        let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
+        assert_eq!(clause, "value IN (?,?,?)");
+        assert_eq!(params, vec![DbValue::Int(1), DbValue::Int(2), DbValue::Int(3)]);
+        let cond = Cond::text_not_in::<&str>("value", &[]);
+        let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
+        assert_eq!(clause, "1=1");
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_long_in_cond_is_bound_as_json() {
+        let values: Vec<i64> = (0..1000).collect();
+        let cond = Cond::integer_not_in("value", &values);
+        let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
+        assert_eq!(clause, "value NOT IN (SELECT value FROM json_each(?))");
+        let json: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        assert_eq!(params, vec![DbValue::Text(format!("[{}]", json.join(",")))]);
+
+        let mut values = vec![DbValue::Text("a\"b\\c\n".to_string()), DbValue::Float(0.5)];
+        values.extend(vec![DbValue::Null; 998]);
+        let cond = Cond::In {
+            field_name: "value".to_string(),
+            values,
+        };
+        let (_, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
+        let json = format!("[\"a\\\"b\\\\c\\u000a\",0.5{}]", ",null".repeat(998));
+        assert_eq!(params, vec![DbValue::Text(json)]);
+
+        let cond = Cond::In {
+            field_name: "value".to_string(),
+            values: vec![DbValue::Blob(vec![0x0a, 0xff]); 1000],
+        };
+        let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
+        assert_eq!(clause, "hex(value) IN (SELECT value FROM json_each(?))");
+        let json = format!("[{}]", vec!["\"0AFF\""; 1000].join(","));
+        assert_eq!(params, vec![DbValue::Text(json)]);
     }
 
     #[test]
@@ -73,8 +202,8 @@ mod cond_parsing_test {
         );
         let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
         assert_eq!(clause, "(value1=?) AND (value2=?)");
-        assert_eq!(i64::from_boxed_db_data(&params[0]), 0xf0);
-        assert_eq!(String::from_boxed_db_data(&params[1]), "str");
+        assert_eq!(params[0], DbValue::Int(0xf0));
+        assert_eq!(params[1], DbValue::from("str"));
     }
 
     #[test]
@@ -85,8 +214,8 @@ mod cond_parsing_test {
         );
         let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
         assert_eq!(clause, "(value1=?) OR (value2=?)");
-        assert_eq!(i64::from_boxed_db_data(&params[0]), 0xf0);
-        assert_eq!(String::from_boxed_db_data(&params[1]), "str");
+        assert_eq!(params[0], DbValue::Int(0xf0));
+        assert_eq!(params[1], DbValue::from("str"));
     }
 
     #[test]
@@ -94,6 +223,957 @@ mod cond_parsing_test {
         let cond = Cond::not(Cond::integer_equal_to("value1", 0xf0));
         let (clause, params) = SQLiteAdaptor::get_condition_stmt_and_params(cond);
         assert_eq!(clause, "NOT (value1=?)");
-        assert_eq!(i64::from_boxed_db_data(&params[0]), 0xf0);
+        assert_eq!(params[0], DbValue::Int(0xf0));
+    }
+}
+
+mod adaptor_test {
+    use crate::SQLiteAdaptor;
+    use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+    use yoshino_core::db::{Aggregate, DbAdaptor, DbDataType, DbError, DbErrorKind, DbValue};
+    use yoshino_core::Schema;
+    use yoshino_core::{Cond, Order, Query, RowID};
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
+    use yoshino_core::{Json, UuidKey};
+    use yoshino_derive::{Schema, YoshinoEnum};
+
+    #[derive(Schema, Debug, Clone)]
+    struct Item {
+        pub id: RowID,
+        pub name: String,
+        pub stock: i64,
+    }
+
+    fn new_item(name: &str, stock: i64) -> Item {
+        Item {
+            id: RowID::NEW,
+            name: name.to_string(),
+            stock,
+        }
+    }
+
+    #[derive(Schema, Debug, Clone)]
+    struct Stock {
+        pub id: RowID,
+        #[yoshino(unique)]
+        pub name: String,
+        pub stock: i64,
+    }
+
+    fn open_test_db() -> SQLiteAdaptor {
+        let mut adaptor = SQLiteAdaptor::open(":memory:").unwrap();
+        adaptor.create_table_for_schema::<Item>().unwrap();
+        adaptor.create_table_for_schema::<Stock>().unwrap();
+        adaptor
+    }
+
+    fn insert_and_get_id<T: Schema>(adaptor: &mut SQLiteAdaptor, record: T) -> i64 {
+        match adaptor.insert_record(record).unwrap() {
+            RowID::ID(id) => id,
+            RowID::NEW => panic!("no row id assigned"),
+        }
+    }
+
+    // count the records of `T` matching a condition
+    fn counter<T: Schema>(adaptor: &mut SQLiteAdaptor) -> impl FnMut(Cond) -> i64 + '_ {
+        move |cond| adaptor.count_with_cond::<T>(cond).unwrap()
+    }
+
+    // list the `name` field of the records of `T` matching a condition, ordered by it
+    fn name_lister<T: Schema>(adaptor: &mut SQLiteAdaptor) -> impl FnMut(Cond) -> Vec<String> + '_ {
+        let index = T::get_fields().iter().position(|(name, _)| name == "name").unwrap();
+        move |cond| {
+            let query = Query::filter(cond).order_by("name", Order::Asc);
+            adaptor
+                .query::<T>(query)
+                .unwrap()
+                .map(|record| match record.unwrap().get_values().swap_remove(index) {
+                    DbValue::Text(name) => name,
+                    value => panic!("the name is not a text: {:?}", value),
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_insert_record_returns_row_id() {
+        let mut adaptor = open_test_db();
+        let first = adaptor.insert_record(new_item("milk", 1)).unwrap();
+        let second = adaptor.insert_record(new_item("cream", 2)).unwrap();
+        assert_eq!(first, RowID::ID(1));
+        assert_eq!(second, RowID::ID(2));
+    }
+
+    #[test]
+    fn test_insert_records() {
+        let mut adaptor = open_test_db();
+        let items = (0..100).map(|i| new_item(&format!("item{}", i), i));
+        assert_eq!(adaptor.insert_records(items).unwrap(), 100);
+        assert_eq!(adaptor.query_all::<Item>().unwrap().count(), 100);
+    }
+
+    #[test]
+    fn test_insert_records_is_atomic() {
+        let mut adaptor = open_test_db();
+        let mut first = new_item("milk", 1);
+        first.id = RowID::ID(1);
+        let mut duplicated = new_item("cream", 2);
+        duplicated.id = RowID::ID(1);
+        assert!(adaptor.insert_records(vec![first, duplicated]).is_err());
+        assert_eq!(adaptor.query_all::<Item>().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_query_with_order_and_pagination() {
+        let mut adaptor = open_test_db();
+        let items = (0..10).map(|i| new_item(&format!("item{}", i), i));
+        adaptor.insert_records(items).unwrap();
+        let query = Query::<Item>::filter(Cond::integer_great_than("stock", 1))
+            .order_by("stock", Order::Desc)
+            .limit(3)
+            .offset(2);
+        let stocks: Vec<i64> = adaptor.query(query).unwrap().map(|x| x.unwrap().stock).collect();
+        assert_eq!(stocks, vec![7, 6, 5]);
+    }
+
+    #[test]
+    fn test_count() {
+        let mut adaptor = open_test_db();
+        let items = (0..10).map(|i| new_item(&format!("item{}", i), i));
+        adaptor.insert_records(items).unwrap();
+        assert_eq!(adaptor.count_all::<Item>().unwrap(), 10);
+        let cond = Cond::integer_great_than_or_equal_to("stock", 7);
+        assert_eq!(adaptor.count_with_cond::<Item>(cond).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let mut adaptor = open_test_db();
+        let items = (1..=4).map(|i| new_item(&format!("item{}", i), i));
+        adaptor.insert_records(items).unwrap();
+        let cond = Cond::integer_great_than("stock", 1);
+        let sum = adaptor.sum_with_cond::<Item>("stock", cond.clone()).unwrap();
+        let min = adaptor.min_with_cond::<Item>("stock", cond.clone()).unwrap();
+        let max = adaptor.max_with_cond::<Item>("stock", cond.clone()).unwrap();
+        let avg = adaptor.avg_with_cond::<Item>("stock", cond).unwrap();
+        assert_eq!(sum, Some(DbValue::Int(9)));
+        assert_eq!(min, Some(DbValue::Int(2)));
+        assert_eq!(max, Some(DbValue::Int(4)));
+        assert_eq!(avg, Some(DbValue::Float(3.0)));
+        let empty = Cond::integer_great_than("stock", 10);
+        assert_eq!(adaptor.sum_with_cond::<Item>("stock", empty).unwrap(), None);
+        assert!(adaptor
+            .aggregate::<Item>(Aggregate::Sum, "name", None)
+            .is_err());
+
+        // integers beyond the precision of f64 are kept exactly
+        let large = (1 << 53) + 1;
+        adaptor.insert_record(new_item("large", large)).unwrap();
+        let max = adaptor.aggregate::<Item>(Aggregate::Max, "stock", None).unwrap();
+        assert_eq!(max, Some(DbValue::Int(large)));
+        let sum = adaptor.aggregate::<Item>(Aggregate::Sum, "stock", None).unwrap();
+        assert_eq!(sum, Some(DbValue::Int(large + 10)));
+    }
+
+    #[test]
+    fn test_affected_rows() {
+        let mut adaptor = open_test_db();
+        let items = (0..10).map(|i| new_item(&format!("item{}", i), i));
+        adaptor.insert_records(items).unwrap();
+        let mut item = adaptor
+            .query_with_cond::<Item>(Cond::integer_equal_to("stock", 3))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        item.stock = 30;
+        let cond = Cond::is_row_id_equal_to(&item).unwrap();
+        assert_eq!(adaptor.update_with_cond(cond, item.clone()).unwrap(), 1);
+        let stale_cond = Cond::integer_equal_to("id", 100);
+        assert_eq!(adaptor.update_with_cond(stale_cond, item).unwrap(), 0);
+        let cond = Cond::integer_less_than("stock", 5);
+        assert_eq!(adaptor.delete_with_cond::<Item>(cond).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_upsert_with_unique_key() {
+        let mut adaptor = open_test_db();
+        let milk = Stock {
+            id: RowID::NEW,
+            name: "milk".to_string(),
+            stock: 1,
+        };
+        let row_id = adaptor.upsert_record(milk.clone()).unwrap();
+        let updated_milk = Stock { stock: 5, ..milk };
+        assert_eq!(adaptor.upsert_record(updated_milk).unwrap(), row_id);
+        let stocks: Vec<Stock> = adaptor
+            .query_all::<Stock>()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(stocks.len(), 1);
+        assert_eq!(stocks[0].id, row_id);
+        assert_eq!(stocks[0].stock, 5);
+    }
+
+    #[test]
+    fn test_upsert_with_row_id() {
+        let mut adaptor = open_test_db();
+        let row_id = adaptor.upsert_record(new_item("milk", 1)).unwrap();
+        assert_eq!(row_id, RowID::ID(1));
+        let mut item = new_item("milk", 3);
+        item.id = row_id;
+        assert_eq!(adaptor.upsert_record(item).unwrap(), row_id);
+        let items: Vec<Item> = adaptor
+            .query_all::<Item>()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].stock, 3);
+    }
+
+    #[test]
+    fn test_get_by_row_id() {
+        let mut adaptor = open_test_db();
+        adaptor.insert_record(new_item("milk", 1)).unwrap();
+        let id = insert_and_get_id(&mut adaptor, new_item("cream", 2));
+        let item = adaptor.get_by_row_id::<Item>(id).unwrap().unwrap();
+        assert_eq!(item.name, "cream");
+        assert!(adaptor.get_by_row_id::<Item>(100).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_find_one() {
+        let mut adaptor = open_test_db();
+        adaptor.insert_record(new_item("milk", 1)).unwrap();
+        adaptor.insert_record(new_item("cream", 1)).unwrap();
+        let item = adaptor
+            .find_one::<Item>(Cond::text_equal_to("name", "milk"))
+            .unwrap();
+        assert_eq!(item.unwrap().name, "milk");
+        let missing = Cond::text_equal_to("name", "apple");
+        assert!(adaptor.find_one::<Item>(missing).unwrap().is_none());
+        let ambiguous = Cond::integer_equal_to("stock", 1);
+        assert!(adaptor.find_one::<Item>(ambiguous).is_err());
+    }
+
+    #[test]
+    fn test_save_and_delete() {
+        let mut adaptor = open_test_db();
+        let mut item = new_item("milk", 1);
+        adaptor.save(&mut item).unwrap();
+        assert_eq!(item.id, RowID::ID(1));
+        item.stock = 10;
+        adaptor.save(&mut item).unwrap();
+        let saved = adaptor.get_by_row_id::<Item>(1).unwrap().unwrap();
+        assert_eq!(saved.stock, 10);
+        adaptor.delete(&item).unwrap();
+        assert!(adaptor.get_by_row_id::<Item>(1).unwrap().is_none());
+        assert!(adaptor.delete(&item).is_err());
+        assert!(adaptor.save(&mut item).is_err());
+    }
+
+    #[test]
+    fn test_drop_and_truncate_table() {
+        let mut adaptor = open_test_db();
+        assert!(adaptor.table_exists_for_schema::<Item>().unwrap());
+        adaptor.insert_record(new_item("milk", 1)).unwrap();
+        adaptor.insert_record(new_item("tea", 1)).unwrap();
+        adaptor.truncate_table_for_schema::<Item>().unwrap();
+        assert!(adaptor.table_exists_for_schema::<Item>().unwrap());
+        assert_eq!(adaptor.count_all::<Item>().unwrap(), 0);
+        // row ids start from 1 again
+        let row_id = adaptor.insert_record(new_item("milk", 1)).unwrap();
+        assert_eq!(row_id, RowID::ID(1));
+        adaptor.drop_table_for_schema::<Item>().unwrap();
+        assert!(!adaptor.table_exists_for_schema::<Item>().unwrap());
+        adaptor.drop_table_for_schema::<Item>().unwrap();
+        assert!(adaptor.count_all::<Item>().is_err());
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let mut adaptor = open_test_db();
+        let milk = Stock {
+            id: RowID::NEW,
+            name: "milk".to_string(),
+            stock: 1,
+        };
+        adaptor.insert_record(milk.clone()).unwrap();
+        let error = adaptor.insert_record(milk).unwrap_err();
+        assert_eq!(error.kind, DbErrorKind::UniqueViolation);
+        assert_eq!(error.code, Some(libsqlite3_sys::SQLITE_CONSTRAINT_UNIQUE));
+        assert!(error.message.contains("UNIQUE constraint failed"));
+        assert!(error.sql.unwrap().starts_with("INSERT INTO "));
+
+        adaptor.drop_table_for_schema::<Item>().unwrap();
+        let error = adaptor.count_all::<Item>().unwrap_err();
+        assert_eq!(error.kind, DbErrorKind::Other);
+        assert!(error.message.contains("no such table"));
+        let error = adaptor.delete(&new_item("milk", 1)).unwrap_err();
+        assert_eq!(error.kind, DbErrorKind::Other);
+    }
+
+    #[test]
+    fn test_failed_statements_are_finalized() {
+        let mut adaptor = open_test_db();
+        let mut stocks = ["milk", "cream"].map(|name| Stock {
+            id: RowID::NEW,
+            name: name.to_string(),
+            stock: 1,
+        });
+        for stock in stocks.iter_mut() {
+            adaptor.save(stock).unwrap();
+        }
+        let error = adaptor.insert_record(stocks[0].clone()).unwrap_err();
+        assert_eq!(error.kind, DbErrorKind::UniqueViolation);
+        stocks[1].name = "milk".to_string();
+        let error = adaptor.save(&mut stocks[1]).unwrap_err();
+        assert_eq!(error.kind, DbErrorKind::UniqueViolation);
+        // the names are not JSON texts, so the statement fails when it's run
+        let cond = Cond::json_path_equal_to("name", "$.a", 1);
+        assert!(adaptor.delete_with_cond::<Stock>(cond).is_err());
+        let next_stmt = unsafe {
+            libsqlite3_sys::sqlite3_next_stmt(adaptor.db_handler, std::ptr::null_mut())
+        };
+        assert!(next_stmt.is_null());
+    }
+
+    #[test]
+    fn test_query_iterator_is_fused() {
+        let mut adaptor = open_test_db();
+        adaptor.insert_record(new_item("milk", 1)).unwrap();
+        let mut items = adaptor.query_all::<Item>().unwrap();
+        assert_eq!(items.next().unwrap().unwrap().name, "milk");
+        assert!(items.next().is_none());
+        assert!(items.next().is_none());
+    }
+
+    #[test]
+    fn test_text_with_nul_round_trip() {
+        let mut adaptor = open_test_db();
+        let id = insert_and_get_id(&mut adaptor, new_item("a\0b\u{3042}", 1));
+        let item = adaptor.get_by_row_id::<Item>(id).unwrap().unwrap();
+        assert_eq!(item.name, "a\0b\u{3042}");
+    }
+
+    #[derive(Schema, Debug, Clone)]
+    struct Measure {
+        pub id: RowID,
+        pub value: Option<f64>,
+    }
+
+    #[test]
+    fn test_nullable_float_round_trip() {
+        let mut adaptor = open_test_db();
+        assert_eq!(
+            Measure::get_field_type("value"),
+            Some(DbDataType::NullableFloat)
+        );
+        adaptor.create_table_for_schema::<Measure>().unwrap();
+        for value in [Some(1.5), None] {
+            adaptor.insert_record(Measure { id: RowID::NEW, value }).unwrap();
+        }
+        let values: Vec<Option<f64>> = adaptor
+            .query_all::<Measure>()
+            .unwrap()
+            .map(|m| m.unwrap().value)
+            .collect();
+        assert_eq!(values, vec![Some(1.5), None]);
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    struct Sensor {
+        pub id: RowID,
+        pub enabled: bool,
+        pub level: u8,
+        pub offset: Option<i16>,
+        pub count: u32,
+        pub reading: f32,
+    }
+
+    #[test]
+    fn test_small_primitive_round_trip() {
+        let mut adaptor = open_test_db();
+        adaptor.create_table_for_schema::<Sensor>().unwrap();
+        let sensor = Sensor {
+            id: RowID::NEW,
+            enabled: true,
+            level: 255,
+            offset: Some(-300),
+            count: u32::MAX,
+            reading: 0.1,
+        };
+        let id = insert_and_get_id(&mut adaptor, sensor.clone());
+        let loaded = adaptor.get_by_row_id::<Sensor>(id).unwrap().unwrap();
+        assert_eq!(loaded, Sensor { id: RowID::ID(id), ..sensor });
+    }
+
+    #[test]
+    fn test_small_primitive_range_check() {
+        let mut adaptor = open_test_db();
+        adaptor.create_table_for_schema::<Sensor>().unwrap();
+        adaptor
+            .execute_sql(
+                "INSERT INTO y_sensor (enabled, level, offset, count, reading) \
+                 VALUES (1, 256, NULL, 0, 0.0), (2, 0, NULL, 0, 0.0);",
+            )
+            .unwrap();
+        let errors: Vec<DbError> = adaptor
+            .query_all::<Sensor>()
+            .unwrap()
+            .map(|sensor| sensor.unwrap_err())
+            .collect();
+        assert!(errors[0].message.starts_with("y_sensor.level: "));
+        assert!(errors[1].message.starts_with("y_sensor.enabled: "));
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    struct Event {
+        pub id: RowID,
+        pub name: String,
+        pub start_at: DateTime<Utc>,
+        pub day: NaiveDate,
+        pub length: Duration,
+        pub ended_at: Option<NaiveDateTime>,
+    }
+
+    fn new_event(name: &str, day: u32) -> Event {
+        let day = NaiveDate::from_ymd_opt(2023, 1, day).unwrap();
+        let start_at = day.and_hms_micro_opt(9, 30, 0, 123456).unwrap();
+        Event {
+            id: RowID::NEW,
+            name: name.to_string(),
+            start_at: Utc.from_utc_datetime(&start_at),
+            day,
+            length: Duration::minutes(90),
+            ended_at: None,
+        }
+    }
+
+    #[test]
+    fn test_datetime_round_trip() {
+        let mut adaptor = open_test_db();
+        adaptor.create_table_for_schema::<Event>().unwrap();
+        let mut event = new_event("launch", 2);
+        event.ended_at = Some(event.start_at.naive_utc() + event.length);
+        let id = insert_and_get_id(&mut adaptor, event.clone());
+        let loaded = adaptor.get_by_row_id::<Event>(id).unwrap().unwrap();
+        assert_eq!(loaded, Event { id: RowID::ID(id), ..event });
+    }
+
+    #[test]
+    fn test_datetime_conds() {
+        let mut adaptor = open_test_db();
+        adaptor.create_table_for_schema::<Event>().unwrap();
+        for (name, day) in [("a", 1), ("b", 10), ("c", 20)] {
+            adaptor.insert_record(new_event(name, day)).unwrap();
+        }
+        let mut names = name_lister::<Event>(&mut adaptor);
+        let date = |day: u32| NaiveDate::from_ymd_opt(2023, 1, day).unwrap();
+        let midnight = |day: u32| Utc.from_utc_datetime(&date(day).and_hms_opt(0, 0, 0).unwrap());
+        assert_eq!(names(Cond::before("start_at", midnight(10))), vec!["a"]);
+        assert_eq!(names(Cond::after("start_at", midnight(10))), vec!["b", "c"]);
+        assert_eq!(names(Cond::between("day", date(1), date(10))), vec!["a", "b"]);
+        assert_eq!(
+            names(Cond::between("length", Duration::hours(1), Duration::hours(2))).len(),
+            3
+        );
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    struct Node {
+        pub id: UuidKey,
+        pub name: String,
+        pub parent: Option<Uuid>,
+    }
+
+    #[test]
+    fn test_uuid_key_generated_at_insert() {
+        let mut adaptor = open_test_db();
+        adaptor.create_table_for_schema::<Node>().unwrap();
+        let root = Node {
+            id: UuidKey::NewV7,
+            name: "root".to_string(),
+            parent: None,
+        };
+        // the key is generated when the record is inserted
+        assert_eq!(adaptor.insert_record(root.clone()).unwrap(), RowID::NEW);
+        let loaded = adaptor.query_all::<Node>().unwrap().next().unwrap().unwrap();
+        let root_id = match loaded.id {
+            UuidKey::ID(id) => id,
+            _ => panic!("the key is not generated"),
+        };
+        assert_eq!(loaded, Node { id: UuidKey::ID(root_id), ..root });
+        assert_eq!(root_id.get_version_num(), 7);
+
+        let mut child = Node {
+            id: UuidKey::NewV4,
+            name: "child".to_string(),
+            parent: Some(root_id),
+        };
+        let child_id = child.id.generate();
+        adaptor.insert_record(child.clone()).unwrap();
+        assert_eq!(child.id, UuidKey::ID(child_id));
+        assert!(adaptor.insert_record(child.clone()).is_err());
+
+        child.name = "renamed".to_string();
+        assert_eq!(adaptor.upsert_record(child.clone()).unwrap(), RowID::NEW);
+        let nodes: Vec<Node> = adaptor
+            .query_all::<Node>()
+            .unwrap()
+            .map(|node| node.unwrap())
+            .collect();
+        assert_eq!(nodes.len(), 2);
+        assert!(nodes.contains(&child));
+
+        let mut parents: Vec<Uuid> = (0..1000).map(|_| Uuid::new_v4()).collect();
+        parents.push(root_id);
+        let cond = Node::columns().parent.is_in(parents);
+        assert_eq!(adaptor.count_with_cond::<Node>(cond).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_save_and_delete_with_uuid_key() {
+        let mut adaptor = open_test_db();
+        adaptor.create_table_for_schema::<Node>().unwrap();
+        let mut node = Node {
+            id: UuidKey::NewV4,
+            name: "leaf".to_string(),
+            parent: None,
+        };
+        adaptor.save(&mut node).unwrap();
+        let id = match node.id {
+            UuidKey::ID(id) => id,
+            _ => panic!("the generated key is not written back"),
+        };
+        assert_eq!(id.get_version_num(), 4);
+
+        node.name = "renamed".to_string();
+        adaptor.save(&mut node).unwrap();
+        assert_eq!(node.id, UuidKey::ID(id));
+        let nodes: Vec<Node> = adaptor
+            .query_all::<Node>()
+            .unwrap()
+            .map(|node| node.unwrap())
+            .collect();
+        assert_eq!(nodes, vec![node.clone()]);
+
+        adaptor.delete(&node).unwrap();
+        assert_eq!(adaptor.count_all::<Node>().unwrap(), 0);
+        let error = adaptor.delete(&node).unwrap_err();
+        assert_eq!(error.kind, DbErrorKind::NotFound);
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    struct Invitation {
+        pub id: RowID,
+        pub token: Option<UuidKey>,
+    }
+
+    #[test]
+    fn test_optional_uuid_key_is_nullable_field() {
+        assert_eq!(
+            Invitation::get_fields()[1],
+            ("token".to_string(), DbDataType::NullableUuid)
+        );
+        assert_eq!(Invitation::get_primary_key_field_name(), Some("id".to_string()));
+        let mut adaptor = open_test_db();
+        adaptor.create_table_for_schema::<Invitation>().unwrap();
+        for token in [None, Some(UuidKey::NewV4)] {
+            let invitation = Invitation { id: RowID::NEW, token };
+            adaptor.insert_record(invitation).unwrap();
+        }
+        let cond = Invitation::columns().token.is_null();
+        assert_eq!(adaptor.count_with_cond::<Invitation>(cond).unwrap(), 1);
+        let cond = Invitation::columns().token.is_not_null();
+        let invitation = adaptor.find_one::<Invitation>(cond).unwrap().unwrap();
+        assert!(matches!(invitation.token, Some(UuidKey::ID(_))));
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Settings {
+        pub theme: String,
+        pub font_size: i64,
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    struct Profile {
+        pub id: RowID,
+        pub settings: Json<Settings>,
+        pub tags: Option<Json<Vec<String>>>,
+    }
+
+    fn new_profile(theme: &str, font_size: i64) -> Profile {
+        let settings = Settings {
+            theme: theme.to_string(),
+            font_size,
+        };
+        Profile {
+            id: RowID::NEW,
+            settings: Json::new(settings).unwrap(),
+            tags: Some(Json::new(vec![theme.to_string()]).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip_and_path_cond() {
+        let mut adaptor = open_test_db();
+        adaptor.create_table_for_schema::<Profile>().unwrap();
+        adaptor.insert_record(new_profile("dark", 12)).unwrap();
+        adaptor.insert_record(new_profile("light", 14)).unwrap();
+        let cond = Cond::json_path_equal_to("settings", "$.theme", "light");
+        let profile = adaptor.find_one::<Profile>(cond).unwrap().unwrap();
+        assert_eq!(profile.settings.font_size, 14);
+        assert_eq!(profile.tags.unwrap().into_inner(), vec!["light".to_string()]);
+        let cond = Cond::json_path_equal_to("settings", "$.font_size", 12);
+        let profile = adaptor.find_one::<Profile>(cond).unwrap().unwrap();
+        assert_eq!(profile.settings.theme, "dark");
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    struct Scoreboard {
+        pub id: RowID,
+        pub scores: Json<std::collections::HashMap<String, i64>>,
+        pub best: Option<Json<(String, i64)>>,
+        pub digest: Json<[u8; 4]>,
+    }
+
+    #[test]
+    fn test_json_fields_with_nested_types() {
+        let mut adaptor = open_test_db();
+        adaptor.create_table_for_schema::<Scoreboard>().unwrap();
+        let scoreboard = Scoreboard {
+            id: RowID::NEW,
+            scores: Json::new([("alice".to_string(), 3), ("bob".to_string(), 5)].into()).unwrap(),
+            best: Some(Json::new(("bob".to_string(), 5)).unwrap()),
+            digest: Json::new([1, 2, 3, 4]).unwrap(),
+        };
+        adaptor.insert_record(scoreboard.clone()).unwrap();
+        let cond = Scoreboard::columns().scores.json_path_eq("$.bob", 5);
+        let record = adaptor.find_one::<Scoreboard>(cond).unwrap().unwrap();
+        assert_eq!(record.scores, scoreboard.scores);
+        assert_eq!(record.best, scoreboard.best);
+        assert_eq!(record.digest, scoreboard.digest);
+
+        // a map with non-string keys can't be serialized
+        let scores: std::collections::HashMap<Vec<u8>, i64> = [(vec![1], 3)].into();
+        let error = Json::new(scores).unwrap_err();
+        assert_eq!(error.kind, DbErrorKind::TypeMismatch);
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Attributes {
+        pub name: String,
+        pub size: i64,
+        pub ratio: f64,
+        pub visible: bool,
+        pub parent: Option<String>,
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    struct Widget {
+        pub id: RowID,
+        pub attributes: Json<Attributes>,
+    }
+
+    #[test]
+    fn test_json_path_cond_compares_scalars() {
+        let mut adaptor = open_test_db();
+        adaptor.create_table_for_schema::<Widget>().unwrap();
+        let attributes = Attributes {
+            name: "12".to_string(),
+            size: 12,
+            ratio: 0.5,
+            visible: true,
+            parent: None,
+        };
+        let widget = Widget {
+            id: RowID::NEW,
+            attributes: Json::new(attributes).unwrap(),
+        };
+        adaptor.insert_record(widget).unwrap();
+        let attributes = Widget::columns().attributes;
+        let mut count = counter::<Widget>(&mut adaptor);
+        // strings only match strings and numbers only match numbers
+        assert_eq!(count(attributes.json_path_eq("$.name", DbValue::from("12"))), 1);
+        assert_eq!(count(attributes.json_path_eq("$.name", DbValue::Int(12))), 0);
+        assert_eq!(count(attributes.json_path_eq("$.size", DbValue::Int(12))), 1);
+        assert_eq!(count(attributes.json_path_eq("$.size", DbValue::Float(12.0))), 1);
+        assert_eq!(count(attributes.json_path_eq("$.size", DbValue::from("12"))), 0);
+        assert_eq!(count(attributes.json_path_eq("$.ratio", DbValue::Float(0.5))), 1);
+        // booleans match 1 and 0
+        assert_eq!(count(attributes.json_path_eq("$.visible", DbValue::Int(1))), 1);
+        assert_eq!(count(attributes.json_path_eq("$.visible", DbValue::Int(0))), 0);
+        // null matches nothing
+        assert_eq!(count(attributes.json_path_eq("$.parent", DbValue::Null)), 0);
+        assert_eq!(count(attributes.json_path_eq("$.parent", DbValue::from("null"))), 0);
+    }
+
+    #[derive(YoshinoEnum, Debug, Clone, Copy, PartialEq)]
+    enum Status {
+        Todo,
+        /// being worked on
+        InProgress,
+        Done,
+    }
+
+    #[derive(YoshinoEnum, Debug, Clone, Copy, PartialEq)]
+    #[yoshino(integer)]
+    enum Priority {
+        Low = 1,
+        High = 10,
+    }
+
+    #[derive(Schema, Debug, Clone, PartialEq)]
+    struct Task {
+        pub id: RowID,
+        pub title: String,
+        pub status: Status,
+        pub priority: Option<Priority>,
+    }
+
+    #[test]
+    fn test_enum_fields() {
+        let mut adaptor = open_test_db();
+        assert_eq!(Task::get_field_type("status"), Some(DbDataType::Text));
+        assert_eq!(
+            Task::get_field_type("priority"),
+            Some(DbDataType::NullableInt)
+        );
+        adaptor.create_table_for_schema::<Task>().unwrap();
+        let tasks = [
+            ("write", Status::InProgress, Some(Priority::High)),
+            ("review", Status::Todo, Some(Priority::Low)),
+            ("ship", Status::Todo, None),
+        ];
+        for (title, status, priority) in tasks {
+            let task = Task {
+                id: RowID::NEW,
+                title: title.to_string(),
+                status,
+                priority,
+            };
+            adaptor.insert_record(task).unwrap();
+        }
+        let cond = Cond::equal_to("status", Status::Todo);
+        assert_eq!(adaptor.count_with_cond::<Task>(cond).unwrap(), 2);
+        let cond = Cond::equal_to("priority", Priority::High);
+        let task = adaptor.find_one::<Task>(cond).unwrap().unwrap();
+        assert_eq!(task.status, Status::InProgress);
+        let cond = Cond::not_equal_to("status", Status::Todo);
+        assert_eq!(adaptor.count_with_cond::<Task>(cond).unwrap(), 1);
+
+        adaptor
+            .execute_sql("UPDATE y_task SET status = 'Cancelled', priority = 5;")
+            .unwrap();
+        let error = adaptor.query_all::<Task>().unwrap().next().unwrap().unwrap_err();
+        assert!(error.message.starts_with("y_task.status: "));
+    }
+
+    #[test]
+    fn test_text_conds() {
+        let mut adaptor = open_test_db();
+        for name in ["Milk", "milk tea", "100% juice", "100 juices", "cream", "a!_b\\c"] {
+            adaptor.insert_record(new_item(name, 1)).unwrap();
+        }
+        let mut names = name_lister::<Item>(&mut adaptor);
+        assert_eq!(names(Cond::text_not_equal_to("name", "cream")).len(), 5);
+        assert_eq!(names(Cond::text_greater_than("name", "cream")), vec!["milk tea"]);
+        assert_eq!(names(Cond::text_less_than("name", "100% z")), vec!["100 juices", "100% juice"]);
+        assert_eq!(
+            names(Cond::text_equal_to_ignore_case("name", "MILK")),
+            vec!["Milk"]
+        );
+        assert_eq!(names(Cond::like("name", "%ea%")), vec!["cream", "milk tea"]);
+        // `!` is a plain character in a raw pattern
+        assert_eq!(names(Cond::like("name", "a!%")), vec!["a!_b\\c"]);
+        assert_eq!(names(Cond::like_with_escape("name", "a!!!_%")), vec!["a!_b\\c"]);
+        assert_eq!(names(Cond::glob("name", "[Mm]ilk*")), vec!["Milk", "milk tea"]);
+        assert_eq!(names(Cond::glob("name", "m*")), vec!["milk tea"]);
+        assert_eq!(names(Cond::starts_with("name", "100%")), vec!["100% juice"]);
+        assert_eq!(names(Cond::ends_with("name", "juice")), vec!["100% juice"]);
+        assert_eq!(names(Cond::contains("name", "0 j")), vec!["100 juices"]);
+        assert_eq!(names(Cond::contains("name", "!_b\\")), vec!["a!_b\\c"]);
+        assert!(names(Cond::contains("name", "a!b")).is_empty());
+    }
+
+    #[derive(Schema, Debug, Clone)]
+    struct Sample {
+        pub id: RowID,
+        pub score: f64,
+        pub digest: Vec<u8>,
+        pub label: Option<String>,
+    }
+
+    #[test]
+    fn test_float_binary_and_null_safe_conds() {
+        let mut adaptor = open_test_db();
+        adaptor.create_table_for_schema::<Sample>().unwrap();
+        let samples = [(0.1 + 0.2, b"ab", Some("x")), (0.5, b"cd", None), (1.0, b"ef", None)];
+        for (score, digest, label) in samples {
+            let sample = Sample {
+                id: RowID::NEW,
+                score,
+                digest: digest.to_vec(),
+                label: label.map(|x| x.to_string()),
+            };
+            adaptor.insert_record(sample).unwrap();
+        }
+        let max = adaptor.aggregate::<Sample>(Aggregate::Max, "score", None).unwrap();
+        assert_eq!(max, Some(DbValue::Float(1.0)));
+        let mut count = counter::<Sample>(&mut adaptor);
+        assert_eq!(count(Cond::float_equal_to("score", 0.3)), 0);
+        assert_eq!(count(Cond::float_approx_equal_to("score", 0.3, 1e-9)), 1);
+        assert_eq!(count(Cond::float_great_than("score", 0.5)), 1);
+        assert_eq!(count(Cond::float_great_than_or_equal_to("score", 0.5)), 2);
+        assert_eq!(count(Cond::float_less_than("score", 0.5)), 1);
+        assert_eq!(count(Cond::float_less_than_or_equal_to("score", 0.5)), 2);
+        assert_eq!(count(Cond::binary_equal_to("digest", b"cd")), 1);
+        assert_eq!(count(Cond::null_safe_equal_to::<String>("label", None)), 2);
+        assert_eq!(count(Cond::null_safe_equal_to("label", Some("x".to_string()))), 1);
+        assert_eq!(count(Sample::columns().label.eq("x")), 1);
+    }
+
+    #[test]
+    fn test_in_and_between_conds() {
+        let mut adaptor = open_test_db();
+        for (name, stock) in [("milk", 1), ("cream", 5), ("apple", 10)] {
+            adaptor.insert_record(new_item(name, stock)).unwrap();
+        }
+        let mut count = counter::<Item>(&mut adaptor);
+        assert_eq!(count(Cond::integer_in("stock", &[1, 10, 100])), 2);
+        assert_eq!(count(Cond::integer_not_in("stock", &[1, 10, 100])), 1);
+        assert_eq!(count(Cond::integer_in("stock", &[])), 0);
+        assert_eq!(count(Cond::text_in("name", &["milk".to_string()])), 1);
+        assert_eq!(count(Cond::text_not_in("name", &["milk", "apple"])), 1);
+        assert_eq!(count(Cond::between("stock", 1, 5)), 2);
+        // more values than the limit of variables in one statement
+        let ids: Vec<i64> = (0..40000).collect();
+        assert_eq!(count(Cond::integer_in("id", &ids)), 3);
+        assert_eq!(count(Cond::integer_not_in("id", &ids[2..])), 1);
+        let mut names: Vec<String> = (0..40000).map(|i| i.to_string()).collect();
+        names.push("cream".to_string());
+        assert_eq!(count(Cond::text_in("name", &names)), 1);
+        assert_eq!(count(Cond::text_not_in("name", &names)), 2);
+    }
+
+    #[test]
+    fn test_typed_columns() {
+        let mut adaptor = open_test_db();
+        for (name, stock) in [("milk", 1), ("cream", 5), ("apple", 10)] {
+            adaptor.insert_record(new_item(name, stock)).unwrap();
+        }
+        let columns = Item::columns();
+        assert_eq!(columns.stock.name(), "stock");
+        let mut count = counter::<Item>(&mut adaptor);
+        assert_eq!(count(columns.name.eq("milk")), 1);
+        assert_eq!(count(columns.name.ne("milk".to_string())), 2);
+        assert_eq!(count(columns.name.is_in(["milk", "apple"])), 2);
+        assert_eq!(count(columns.stock.gt(1) & columns.stock.le(10)), 2);
+        assert_eq!(count(columns.stock.lt(5) | columns.stock.ge(10)), 2);
+        assert_eq!(count(columns.stock.between(2, 10)), 2);
+        assert_eq!(count(columns.stock.is_in([1, 5])), 2);
+        assert_eq!(count(columns.stock.not_in(vec![1, 5])), 1);
+        assert_eq!(count(columns.name.starts_with("c") | columns.name.contains("pl")), 2);
+        assert_eq!(count(columns.id.eq(RowID::ID(1))), 1);
+    }
+
+    #[test]
+    fn test_typed_enum_columns() {
+        let mut adaptor = open_test_db();
+        adaptor.create_table_for_schema::<Task>().unwrap();
+        for (title, priority) in [("write", Some(Priority::High)), ("ship", None)] {
+            let task = Task {
+                id: RowID::NEW,
+                title: title.to_string(),
+                status: Status::Todo,
+                priority,
+            };
+            adaptor.insert_record(task).unwrap();
+        }
+        let columns = Task::columns();
+        let mut count = counter::<Task>(&mut adaptor);
+        assert_eq!(count(columns.status.eq(Status::Todo)), 2);
+        assert_eq!(count(columns.priority.eq(Priority::High)), 1);
+        assert_eq!(count(columns.priority.eq(None)), 1);
+        assert_eq!(count(columns.priority.is_not_null()), 1);
+        assert_eq!(count(columns.title.eq_ignore_case("SHIP")), 1);
+    }
+
+    #[test]
+    fn test_decode_error_reports_field_and_continues() {
+        let mut adaptor = open_test_db();
+        adaptor
+            .execute_sql("INSERT INTO y_item (name, stock) VALUES ('milk', 'many');")
+            .unwrap();
+        adaptor.insert_record(new_item("cream", 2)).unwrap();
+        let mut iter = adaptor.query_all::<Item>().unwrap();
+        let error = iter.next().unwrap().unwrap_err();
+        assert_eq!(error.kind, DbErrorKind::TypeMismatch);
+        assert!(error.message.starts_with("y_item.stock: "));
+        assert_eq!(iter.next().unwrap().unwrap().name, "cream");
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_transaction_commit() {
+        let mut adaptor = open_test_db();
+        adaptor
+            .transaction(|tx| {
+                tx.insert_record(new_item("milk", 1))?;
+                tx.insert_record(new_item("cream", 2))
+            })
+            .unwrap();
+        assert_eq!(adaptor.query_all::<Item>().unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_transaction_rollback_on_error() {
+        let mut adaptor = open_test_db();
+        let result: Result<(), DbError> = adaptor.transaction(|tx| {
+            tx.insert_record(new_item("milk", 1))?;
+            Err(DbError::new(DbErrorKind::Other, "abort".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(adaptor.query_all::<Item>().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_transaction_rollback_on_panic() {
+        let mut adaptor = open_test_db();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _ = adaptor.transaction(|tx| -> Result<(), DbError> {
+                tx.insert_record(new_item("milk", 1))?;
+                panic!("abort");
+            });
+        }));
+        assert!(result.is_err());
+        assert_eq!(adaptor.query_all::<Item>().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_nested_transaction_rollback() {
+        let mut adaptor = open_test_db();
+        adaptor
+            .transaction(|tx| {
+                tx.insert_record(new_item("milk", 1))?;
+                let inner: Result<(), DbError> = tx.transaction(|tx| {
+                    tx.insert_record(new_item("cream", 2))?;
+                    Err(DbError::new(DbErrorKind::Other, "abort".to_string()))
+                });
+                assert!(inner.is_err());
+                Ok(())
+            })
+            .unwrap();
+        let items: Vec<Item> = adaptor
+            .query_all::<Item>()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "milk");
     }
 }
@@ -1,7 +1,10 @@
 /// Internal code for authenticate a user.
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use sha2::{Digest, Sha256};
-use yoshino_core::{db::DbData, TextField};
+use yoshino_core::{
+    db::{DbDataType, DbError, DbErrorKind, DbValue},
+    Field,
+};
 
 /// To indicate how the useer credential is hashed
 #[derive(Clone, Debug)]
@@ -47,7 +50,8 @@ impl UserCredential {
     }
 }
 
-impl TextField for UserCredential {
+impl Field for UserCredential {
+    type Storage = String;
     fn to_db_data(&self) -> String {
         let mut buf = BytesMut::new();
         match &self.hash_type {
@@ -61,32 +65,52 @@ impl TextField for UserCredential {
         };
         base64::encode(buf)
     }
-    fn from_db_data(data: &Box<dyn DbData>) -> UserCredential {
-        let data_str = <String as DbData>::from_boxed_db_data(data);
-        let mut buf = Bytes::from(base64::decode(data_str).unwrap());
+    fn from_db_data(data: &DbValue) -> Result<UserCredential, DbError> {
+        let data_str = String::from_db_data(data)?;
+        let mut buf = Bytes::from(base64::decode(data_str).map_err(|e| {
+            get_invalid_credential_error(&format!("not a base64 string ({})", e))
+        })?);
+        if buf.remaining() < 4 {
+            return Err(get_invalid_credential_error("missing the magic number"));
+        }
         let magic_number = buf.get_i32();
         match magic_number {
             0x35A256 => {
-                let salt_len = buf.get_u32() as usize;
-                let mut salt = BytesMut::with_capacity(salt_len);
-                for _i in 0..salt_len {
-                    salt.put_u8(buf.get_u8())
-                }
-                let data_len = buf.get_u32() as usize;
-                let mut data = BytesMut::with_capacity(data_len);
-                for _i in 0..data_len {
-                    data.put_u8(buf.get_u8())
-                }
-                UserCredential {
-                    data: Bytes::from(data),
-                    hash_type: UserCredentialHashType::Sha256WithSalt(Bytes::from(salt)),
-                }
-            }
-            _ => {
-                panic!("Unsupported user credential type");
+                let salt = get_length_prefixed_bytes(&mut buf)?;
+                let data = get_length_prefixed_bytes(&mut buf)?;
+                Ok(UserCredential {
+                    data,
+                    hash_type: UserCredentialHashType::Sha256WithSalt(salt),
+                })
             }
+            _ => Err(get_invalid_credential_error(&format!(
+                "unsupported credential type {:#x}",
+                magic_number
+            ))),
         }
     }
+    fn db_field_type() -> DbDataType {
+        DbDataType::Text
+    }
+}
+
+/// Read a chunk of bytes prefixed with its length as an u32.
+fn get_length_prefixed_bytes(buf: &mut Bytes) -> Result<Bytes, DbError> {
+    if buf.remaining() < 4 {
+        return Err(get_invalid_credential_error("truncated data"));
+    }
+    let len = buf.get_u32() as usize;
+    if buf.remaining() < len {
+        return Err(get_invalid_credential_error("truncated data"));
+    }
+    Ok(buf.split_to(len))
+}
+
+fn get_invalid_credential_error(reason: &str) -> DbError {
+    DbError::new(
+        DbErrorKind::TypeMismatch,
+        format!("Invalid user credential: {}", reason),
+    )
 }
 
 #[cfg(test)]
@@ -114,8 +138,21 @@ mod tests {
         );
 
         let data = ground_truth.to_db_data();
-        let boxed_data: Box<(dyn DbData + 'static)> = Box::new(data);
-        let new_user = UserCredential::from_db_data(&boxed_data);
+        let new_user = UserCredential::from_db_data(&DbValue::Text(data)).unwrap();
         assert!(new_user.validate_credential(Bytes::from(plain_text)));
     }
+
+    #[test]
+    fn test_corrupted_user_credential() {
+        let ground_truth = UserCredential::new(
+            Bytes::from("this_is_the_pain_text"),
+            UserCredentialHashType::Sha256WithSalt(Bytes::from("salt")),
+        );
+        let mut data = ground_truth.to_db_data();
+        data.truncate(data.len() - 8);
+        assert!(UserCredential::from_db_data(&DbValue::Text(data)).is_err());
+        assert!(UserCredential::from_db_data(&DbValue::Text("!!".to_string())).is_err());
+        assert!(UserCredential::from_db_data(&DbValue::Text("AAAAAA==".to_string())).is_err());
+        assert!(UserCredential::from_db_data(&DbValue::Int(1)).is_err());
+    }
 }
@@ -5,7 +5,7 @@ mod authentication;
 pub use authentication::UserCredential;
 pub use authentication::UserCredentialHashType;
 use bytes::Bytes;
-use yoshino_core::{RowID, TextField};
+use yoshino_core::RowID;
 use yoshino_derive::Schema;
 
 /// The user representation for login purpose